@@ -0,0 +1,166 @@
+package main
+
+import (
+	"bufio"
+	"fmt"
+	"os"
+	"path/filepath"
+	"strconv"
+	"strings"
+)
+
+const cargoManifest = "Cargo.toml"
+
+// cargoTables are the tables of a Cargo manifest read as variables, in
+// order: keys in later tables override the ones in earlier tables.
+var cargoTables = [...]string{"env", "package.metadata.dotenv"}
+
+// findCargoManifest returns the path to the Cargo.toml file in the current
+// directory or the closest directory above it.
+func findCargoManifest() (string, error) {
+	dir, err := os.Getwd()
+	if err != nil {
+		return "", err
+	}
+
+	for {
+		fp := filepath.Join(dir, cargoManifest)
+		if isFile(fp) {
+			return fp, nil
+		}
+
+		parent := filepath.Dir(dir)
+		if parent == dir {
+			return "", fmt.Errorf("no %s found in %s or any directory above it", cargoManifest, dir)
+		}
+
+		dir = parent
+	}
+}
+
+// loadCargoEnv reads the variables set in the [env] and
+// [package.metadata.dotenv] tables of the closest Cargo.toml. Only keys
+// with string, number or boolean values are supported; keys are used as
+// they're written.
+func loadCargoEnv() (map[string]string, string, error) {
+	fp, err := findCargoManifest()
+	if err != nil {
+		return nil, "", err
+	}
+
+	data, err := loadFile(fp)
+	if err != nil {
+		return nil, fp, err
+	}
+
+	tables := make(map[string]map[string]string)
+	current := ""
+	sc := bufio.NewScanner(data)
+
+	for line := 1; sc.Scan(); line++ {
+		text := strings.TrimSpace(sc.Text())
+		if text == "" || startswith(text, "#") {
+			continue
+		}
+
+		if startswith(text, "[") {
+			current, err = tomlTable(text)
+			if err != nil {
+				return nil, fp, fmt.Errorf("%s:%d: %s", fp, line, err.Error())
+			}
+
+			continue
+		}
+
+		if !contains(cargoTables[:], current) {
+			continue
+		}
+
+		idx := strings.Index(text, "=")
+		if idx <= 0 {
+			return nil, fp, fmt.Errorf("%s:%d: expected key = value", fp, line)
+		}
+
+		key, _ := unquote(strings.TrimSpace(text[:idx]))
+
+		value, err := tomlValue(strings.TrimSpace(text[idx+1:]))
+		if err != nil {
+			return nil, fp, fmt.Errorf("%s:%d: %s: %s", fp, line, key, err.Error())
+		}
+
+		if tables[current] == nil {
+			tables[current] = make(map[string]string)
+		}
+
+		tables[current][key] = value
+	}
+
+	if err := sc.Err(); err != nil {
+		return nil, fp, err
+	}
+
+	ev := make(map[string]string)
+	for _, name := range cargoTables {
+		for k, v := range tables[name] {
+			ev[k] = v
+		}
+	}
+
+	return ev, fp, nil
+}
+
+// tomlTable returns the name of the table in a "[table]" or "[[table]]"
+// header, which may be followed by a comment.
+func tomlTable(header string) (string, error) {
+	name := strings.TrimLeft(header, "[")
+
+	end := strings.Index(name, "]")
+	if end < 0 {
+		return "", fmt.Errorf("unclosed table header %q", header)
+	}
+
+	return strings.TrimSpace(name[:end]), nil
+}
+
+// tomlValue reads a single-line TOML value: a basic "string", a literal
+// 'string', or a bare number or boolean, which is used as written. A
+// trailing comment is ignored.
+func tomlValue(raw string) (string, error) {
+	if startswith(raw, `"""`) || startswith(raw, "'''") {
+		return "", fmt.Errorf("multi-line strings are not supported")
+	}
+
+	switch {
+	case startswith(raw, `"`):
+		for end := 1; end < len(raw); end++ {
+			if raw[end] == '\\' {
+				end++
+				continue
+			}
+
+			if raw[end] == '"' {
+				return strconv.Unquote(raw[:end+1])
+			}
+		}
+
+		return "", fmt.Errorf("unterminated string")
+
+	case startswith(raw, "'"):
+		end := strings.Index(raw[1:], "'")
+		if end < 0 {
+			return "", fmt.Errorf("unterminated string")
+		}
+
+		return raw[1 : end+1], nil
+	}
+
+	if idx := strings.Index(raw, "#"); idx >= 0 {
+		raw = strings.TrimSpace(raw[:idx])
+	}
+
+	if raw == "" || strings.ContainsAny(raw, "{[") {
+		return "", fmt.Errorf("only string, number and boolean values are supported")
+	}
+
+	return raw, nil
+}
@@ -0,0 +1,63 @@
+package main
+
+import "testing"
+
+func TestTomlValue(t *testing.T) {
+	cases := []struct {
+		raw     string
+		want    string
+		wantErr bool
+	}{
+		{raw: `"hello"`, want: "hello"},
+		{raw: `"a \"b\" # c" # comment`, want: `a "b" # c`},
+		{raw: `"tab\there \u00e9"`, want: "tab\there é"},
+		{raw: `'C:\path' # comment`, want: `C:\path`},
+		{raw: "42 # answer", want: "42"},
+		{raw: "true", want: "true"},
+		{raw: `"unterminated`, wantErr: true},
+		{raw: `'unterminated`, wantErr: true},
+		{raw: `"""multi-line`, wantErr: true},
+		{raw: `'''multi-line'''`, wantErr: true},
+		{raw: "[1, 2]", wantErr: true},
+		{raw: "{ a = 1 }", wantErr: true},
+		{raw: "# nothing", wantErr: true},
+	}
+
+	for _, tc := range cases {
+		got, err := tomlValue(tc.raw)
+		if (err != nil) != tc.wantErr {
+			t.Errorf("tomlValue(%q) error = %v, want error: %v", tc.raw, err, tc.wantErr)
+			continue
+		}
+
+		if got != tc.want {
+			t.Errorf("tomlValue(%q) = %q, want %q", tc.raw, got, tc.want)
+		}
+	}
+}
+
+func TestTomlTable(t *testing.T) {
+	cases := []struct {
+		header  string
+		want    string
+		wantErr bool
+	}{
+		{header: "[env]", want: "env"},
+		{header: "[env] # variables", want: "env"},
+		{header: "[ package.metadata.dotenv ]", want: "package.metadata.dotenv"},
+		{header: "[[bin]]", want: "bin"},
+		{header: "[env", wantErr: true},
+	}
+
+	for _, tc := range cases {
+		got, err := tomlTable(tc.header)
+		if (err != nil) != tc.wantErr {
+			t.Errorf("tomlTable(%q) error = %v, want error: %v", tc.header, err, tc.wantErr)
+			continue
+		}
+
+		if got != tc.want {
+			t.Errorf("tomlTable(%q) = %q, want %q", tc.header, got, tc.want)
+		}
+	}
+}
@@ -0,0 +1,36 @@
+package main
+
+// diffEnv compares two sets of variables and returns one line per key that
+// differs, sorted by key: "+ KEY=value" when it was added in b, "- KEY=value"
+// when it was removed from a, and "~ KEY=old -> new" when its value changed.
+// Values of variables that look like secrets are masked.
+func diffEnv(a, b map[string]string) []string {
+	all := make(map[string]string, len(a)+len(b))
+	for k := range a {
+		all[k] = ""
+	}
+
+	for k := range b {
+		all[k] = ""
+	}
+
+	var out []string
+
+	for _, k := range sortedKeys(all) {
+		oldval, inA := a[k]
+		newval, inB := b[k]
+
+		switch {
+		case !inA:
+			out = append(out, "+ "+k+"="+maskValue(k, newval))
+
+		case !inB:
+			out = append(out, "- "+k+"="+maskValue(k, oldval))
+
+		case oldval != newval:
+			out = append(out, "~ "+k+"="+maskValue(k, oldval)+" -> "+maskValue(k, newval))
+		}
+	}
+
+	return out
+}
@@ -0,0 +1,34 @@
+package main
+
+import (
+	"reflect"
+	"testing"
+)
+
+func TestDiffEnv(t *testing.T) {
+	a := map[string]string{"HOST": "localhost", "PORT": "80", "OLD": "x", "API_TOKEN": "old-token", "SAME": "1"}
+	b := map[string]string{"HOST": "example.com", "PORT": "80", "NEW": "y", "API_TOKEN": "new-token", "SAME": "1"}
+
+	want := []string{
+		"~ API_TOKEN=**** -> ****",
+		"~ HOST=localhost -> example.com",
+		"+ NEW=y",
+		"- OLD=x",
+	}
+
+	if got := diffEnv(a, b); !reflect.DeepEqual(got, want) {
+		t.Errorf("diffEnv() = %q, want %q", got, want)
+	}
+
+	if got := diffEnv(a, a); len(got) != 0 {
+		t.Errorf("diffEnv() of the same variables = %q, want no changes", got)
+	}
+
+	maskReveal = 2
+	defer func() { maskReveal = 0 }()
+
+	want = []string{"+ DB_PASSWORD=hu…r2", "- SECRET=****"}
+	if got := diffEnv(map[string]string{"SECRET": "abc"}, map[string]string{"DB_PASSWORD": "hunter2"}); !reflect.DeepEqual(got, want) {
+		t.Errorf("diffEnv() with --mask-reveal 2 = %q, want %q", got, want)
+	}
+}
@@ -0,0 +1,112 @@
+package main
+
+import (
+	"fmt"
+	"io"
+	"os"
+	"os/exec"
+	"path/filepath"
+	"strconv"
+	"sync"
+)
+
+// runEach runs the command once per env file matching the --each pattern,
+// with up to --concurrency runs at the same time. Each run is a new dotenv
+// process using that file as its environment and the rest of the explicit
+// flags, the ones given in the command line: it reads the rc file itself,
+// so the environment is resolved exactly like a single run would. It
+// returns the exit code to use: zero only if every run succeeded.
+func runEach(flags, explicit cliflags, args []string) int {
+	pattern := flags.value("--each")
+
+	files, err := filepath.Glob(pattern)
+	if err != nil {
+		errexit("Invalid pattern %q for --each: %s", pattern, err.Error())
+	}
+
+	if len(files) == 0 {
+		errexit("No env files match %q", pattern)
+	}
+
+	concurrency := 1
+	if flags.isSet("--concurrency") {
+		concurrency, err = strconv.Atoi(flags.value("--concurrency"))
+		if err != nil || concurrency < 1 {
+			errexit("Invalid value %q for --concurrency, expected a number greater than zero", flags.value("--concurrency"))
+		}
+	}
+
+	self, err := os.Executable()
+	if err != nil {
+		errexit("Unable to find the dotenv executable: %s", err.Error())
+	}
+
+	var (
+		wg      sync.WaitGroup
+		sem     = make(chan struct{}, concurrency)
+		results = make([]error, len(files))
+	)
+
+	for pos, fp := range files {
+		if abs, err := filepath.Abs(fp); err == nil {
+			fp = abs
+		}
+
+		cmd := exec.Command(self, eachArgs(explicit, fp, args)...)
+		cmd.Stdout = os.Stdout
+		cmd.Stderr = os.Stderr
+
+		// stdin can't be shared between runs happening at the same time
+		if concurrency == 1 {
+			cmd.Stdin = os.Stdin
+		}
+
+		wg.Add(1)
+		sem <- struct{}{}
+
+		go func(pos int) {
+			defer wg.Done()
+			results[pos] = cmd.Run()
+			<-sem
+		}(pos)
+	}
+
+	wg.Wait()
+
+	return eachExitCode(os.Stderr, files, results)
+}
+
+// eachArgs returns the arguments of the dotenv run for the env file fp:
+// the file as its environment, the flags other than --each and
+// --concurrency, and the command.
+func eachArgs(flags cliflags, fp string, args []string) []string {
+	argv := []string{"--environment", fp}
+
+	for _, f := range flags {
+		if f.name == "--each" || f.name == "--concurrency" {
+			continue
+		}
+
+		argv = append(argv, f.name)
+		if findFlag(f.name).hasValue {
+			argv = append(argv, f.value)
+		}
+	}
+
+	return append(append(argv, "--"), args...)
+}
+
+// eachExitCode reports every failed run to w and returns the exit code of
+// --each: zero only if every run succeeded, one otherwise.
+func eachExitCode(w io.Writer, files []string, results []error) int {
+	code := 0
+
+	for pos, err := range results {
+		if err != nil {
+			fmt.Fprintf(w, "[dotenv] command failed with env file %q: %s\n", files[pos], err.Error())
+			code = 1
+		}
+	}
+
+	return code
+}
@@ -0,0 +1,65 @@
+package main
+
+import (
+	"bytes"
+	"errors"
+	"reflect"
+	"strings"
+	"testing"
+)
+
+func TestEachArgs(t *testing.T) {
+	flags := cliflags{
+		{name: "--each", value: "envs/*.env"},
+		{name: "--concurrency", value: "2"},
+		{name: "--strict"},
+		{name: "--keep", value: "TZ"},
+	}
+
+	cases := []struct {
+		fp   string
+		want []string
+	}{
+		{fp: "/app/envs/a.env", want: []string{"--environment", "/app/envs/a.env", "--strict", "--keep", "TZ", "--", "make", "test"}},
+		{fp: "/app/envs/b.env", want: []string{"--environment", "/app/envs/b.env", "--strict", "--keep", "TZ", "--", "make", "test"}},
+	}
+
+	for _, tc := range cases {
+		if got := eachArgs(flags, tc.fp, []string{"make", "test"}); !reflect.DeepEqual(got, tc.want) {
+			t.Errorf("eachArgs(%q) = %q, want %q", tc.fp, got, tc.want)
+		}
+	}
+}
+
+func TestEachExitCode(t *testing.T) {
+	files := []string{"a.env", "b.env"}
+	failed := errors.New("exit status 3")
+
+	cases := []struct {
+		results []error
+		want    int
+		failed  []string
+	}{
+		{results: []error{nil, nil}, want: 0},
+		{results: []error{nil, failed}, want: 1, failed: []string{"b.env"}},
+		{results: []error{failed, failed}, want: 1, failed: []string{"a.env", "b.env"}},
+	}
+
+	for _, tc := range cases {
+		var out bytes.Buffer
+
+		if got := eachExitCode(&out, files, tc.results); got != tc.want {
+			t.Errorf("eachExitCode(%v) = %d, want %d", tc.results, got, tc.want)
+		}
+
+		if got := strings.Count(out.String(), "\n"); got != len(tc.failed) {
+			t.Errorf("eachExitCode(%v) reported %d failures, want %d: %q", tc.results, got, len(tc.failed), out.String())
+		}
+
+		for _, fp := range tc.failed {
+			if !strings.Contains(out.String(), fp) {
+				t.Errorf("eachExitCode(%v) didn't report %s: %q", tc.results, fp, out.String())
+			}
+		}
+	}
+}
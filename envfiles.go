@@ -0,0 +1,110 @@
+package main
+
+import "fmt"
+
+// fileSources are the settings that decide which env files are loaded.
+type fileSources struct {
+	dotenv       string   // the value of $DOTENV
+	environments []string // the --environment values, in order
+	files        []string // the --file values, in order, patterns included
+	ext          string   // the extension of environments in the user's directories
+	nextTo       string   // with --env-next-to-command, the command
+	cargo        bool     // whether --from-cargo-toml is set
+	requireFile  bool     // whether --require-file is set
+}
+
+// envFiles returns the env files to load, in order, each one overriding the
+// variables set by the ones before it: $DOTENV or the --environment files,
+// or ".env" and ".env.local" when none of them nor any --file is given,
+// followed by the --file ones. Environments are looked up in the user's
+// directories unless they're paths; missing ones are skipped with a warning
+// unless none of them exists.
+func envFiles(src fileSources) ([]string, error) {
+	evfile := src.dotenv
+
+	var named []string
+	if len(src.environments) > 0 {
+		var candidates []string
+		for _, venv := range src.environments {
+			fp := venv
+			if !startswith(venv, "/") && !startswith(venv, "./") {
+				home, found, err := envFilePresentInHome(venv, src.ext)
+				if err != nil {
+					return nil, fmt.Errorf("unable to look for environment %q in %s: %s", venv, dotenvLocations, err.Error())
+				}
+
+				if !found {
+					home, found = envFilePresentInConfig(venv, src.ext)
+				}
+
+				if found {
+					fp = home
+				}
+			}
+
+			candidates = append(candidates, fp)
+
+			exists := fp == stdinFile || startswith(fp, gitPrefix)
+			if expanded, err := expand(fp); err == nil && !exists {
+				exists = isFile(expanded)
+			}
+
+			if exists {
+				named = append(named, fp)
+			} else if len(src.environments) > 1 {
+				warnf("environment %q not found, skipping it", venv)
+			}
+		}
+
+		if len(named) == 0 {
+			named = candidates
+		}
+
+		evfile = ""
+	}
+
+	var local string
+	if evfile == "" && len(named) == 0 && len(src.files) == 0 {
+		evfile = ".env"
+
+		if src.nextTo != "" {
+			if fp, found := envFileNextTo(src.nextTo); found {
+				evfile = fp
+			}
+		}
+
+		// a ".env.local" next to the default file overrides it, and
+		// is used on its own when there's no ".env"
+		if fp := evfile + ".local"; isFile(fp) {
+			if isFile(evfile) {
+				local = fp
+			} else {
+				evfile = fp
+			}
+		}
+
+		// the manifest is enough as a source of variables on its own,
+		// unless a file is required
+		if src.cargo && !src.requireFile && !isFile(evfile) {
+			evfile = ""
+		}
+	}
+
+	var out []string
+	if evfile != "" {
+		out = append(out, evfile)
+	}
+
+	out = append(out, named...)
+
+	if local != "" {
+		out = append(out, local)
+	}
+
+	files, err := expandFileGlobs(src.files)
+	if err != nil {
+		return nil, err
+	}
+
+	return append(out, files...), nil
+}
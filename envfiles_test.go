@@ -0,0 +1,202 @@
+package main
+
+import (
+	"io/ioutil"
+	"os"
+	"path/filepath"
+	"reflect"
+	"testing"
+)
+
+func TestEnvFiles(t *testing.T) {
+	cases := []struct {
+		name    string
+		present []string
+		src     fileSources
+		want    []string
+		wantErr bool
+	}{
+		{name: "no files", want: []string{".env"}},
+		{name: "base only", present: []string{".env"}, want: []string{".env"}},
+		{name: "local only", present: []string{".env.local"}, want: []string{".env.local"}},
+		{name: "base and local", present: []string{".env", ".env.local"}, want: []string{".env", ".env.local"}},
+		{
+			name:    "$DOTENV",
+			present: []string{".env", ".env.local", "custom.env"},
+			src:     fileSources{dotenv: "custom.env"},
+			want:    []string{"custom.env"},
+		},
+		{
+			name:    "environment over $DOTENV",
+			present: []string{"custom.env", "a.env"},
+			src:     fileSources{dotenv: "custom.env", environments: []string{"./a.env"}},
+			want:    []string{"./a.env"},
+		},
+		{
+			name:    "missing environments are skipped",
+			present: []string{"a.env"},
+			src:     fileSources{environments: []string{"./missing.env", "./a.env"}},
+			want:    []string{"./a.env"},
+		},
+		{
+			name: "no environment found",
+			src:  fileSources{environments: []string{"./missing.env"}},
+			want: []string{"./missing.env"},
+		},
+		{
+			name:    "files replace the default",
+			present: []string{".env", "b.env"},
+			src:     fileSources{files: []string{"b.env"}},
+			want:    []string{"b.env"},
+		},
+		{
+			name:    "environment then files",
+			present: []string{"a.env", "b.env", "d.local", "c.local"},
+			src:     fileSources{environments: []string{"./a.env"}, files: []string{"b.env", "*.local"}},
+			want:    []string{"./a.env", "b.env", "c.local", "d.local"},
+		},
+		{name: "pattern matching nothing", src: fileSources{files: []string{"*.env"}}, wantErr: true},
+		{name: "Cargo.toml on its own", src: fileSources{cargo: true}, want: nil},
+		{name: "Cargo.toml and .env", present: []string{".env"}, src: fileSources{cargo: true}, want: []string{".env"}},
+		{name: "--require-file without .env", src: fileSources{cargo: true, requireFile: true}, want: []string{".env"}},
+		{name: "--require-file with .env", present: []string{".env"}, src: fileSources{cargo: true, requireFile: true}, want: []string{".env"}},
+	}
+
+	cwd, err := os.Getwd()
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer os.Chdir(cwd)
+
+	warnOutput = ioutil.Discard
+	defer func() { warnOutput = os.Stderr }()
+
+	for _, tc := range cases {
+		dir, err := ioutil.TempDir("", "dotenv")
+		if err != nil {
+			t.Fatal(err)
+		}
+		defer os.RemoveAll(dir)
+
+		for _, name := range tc.present {
+			if err := ioutil.WriteFile(filepath.Join(dir, name), []byte("KEY=value\n"), 0644); err != nil {
+				t.Fatal(err)
+			}
+		}
+
+		if err := os.Chdir(dir); err != nil {
+			t.Fatal(err)
+		}
+
+		got, err := envFiles(tc.src)
+		if (err != nil) != tc.wantErr {
+			t.Errorf("%s: envFiles() error = %v, want error: %v", tc.name, err, tc.wantErr)
+			continue
+		}
+
+		if !tc.wantErr && !reflect.DeepEqual(got, tc.want) {
+			t.Errorf("%s: envFiles() = %q, want %q", tc.name, got, tc.want)
+		}
+	}
+}
+
+func TestRequireFile(t *testing.T) {
+	dir, err := ioutil.TempDir("", "dotenv")
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer os.RemoveAll(dir)
+
+	cwd, err := os.Getwd()
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer os.Chdir(cwd)
+
+	if err := os.Chdir(dir); err != nil {
+		t.Fatal(err)
+	}
+
+	src := fileSources{cargo: true, requireFile: true}
+
+	// the default file is kept in the list, so loading it fails
+	files, err := envFiles(src)
+	if err != nil {
+		t.Fatalf("envFiles() error = %v", err)
+	}
+
+	if _, _, err := loadEnvFiles(files, parseOptions{}); err == nil {
+		t.Errorf("loadEnvFiles(%q) without an env file succeeded, want an error", files)
+	}
+
+	if err := ioutil.WriteFile(".env", []byte("KEY=value\n"), 0644); err != nil {
+		t.Fatal(err)
+	}
+
+	files, err = envFiles(src)
+	if err != nil {
+		t.Fatalf("envFiles() error = %v", err)
+	}
+
+	if vars, _, err := loadEnvFiles(files, parseOptions{}); err != nil || vars["KEY"] != "value" {
+		t.Errorf("loadEnvFiles(%q) = %v, %v, want KEY set", files, vars, err)
+	}
+}
+
+func TestDotenvLocal(t *testing.T) {
+	cases := []struct {
+		name  string
+		files map[string]string
+		want  map[string]string
+	}{
+		{
+			name:  "base only",
+			files: map[string]string{".env": "HOST=localhost\nPORT=80\n"},
+			want:  map[string]string{"HOST": "localhost", "PORT": "80"},
+		},
+		{
+			name:  "local only",
+			files: map[string]string{".env.local": "PORT=8080\n"},
+			want:  map[string]string{"PORT": "8080"},
+		},
+		{
+			name:  "local overrides base",
+			files: map[string]string{".env": "HOST=localhost\nPORT=80\n", ".env.local": "PORT=8080\nDEBUG=true\n"},
+			want:  map[string]string{"HOST": "localhost", "PORT": "8080", "DEBUG": "true"},
+		},
+	}
+
+	cwd, err := os.Getwd()
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer os.Chdir(cwd)
+
+	for _, tc := range cases {
+		dir, err := ioutil.TempDir("", "dotenv")
+		if err != nil {
+			t.Fatal(err)
+		}
+		defer os.RemoveAll(dir)
+
+		for name, data := range tc.files {
+			if err := ioutil.WriteFile(filepath.Join(dir, name), []byte(data), 0644); err != nil {
+				t.Fatal(err)
+			}
+		}
+
+		if err := os.Chdir(dir); err != nil {
+			t.Fatal(err)
+		}
+
+		files, err := envFiles(fileSources{})
+		if err != nil {
+			t.Errorf("%s: envFiles() error = %v", tc.name, err)
+			continue
+		}
+
+		if got, _, err := loadEnvFiles(files, parseOptions{}); err != nil || !reflect.DeepEqual(got, tc.want) {
+			t.Errorf("%s: loadEnvFiles(%q) = %v, %v, want %v", tc.name, files, got, err, tc.want)
+		}
+	}
+}
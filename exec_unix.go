@@ -0,0 +1,21 @@
+// +build !windows
+
+package main
+
+import (
+	"os/exec"
+	"syscall"
+)
+
+// execCommand replaces the dotenv process with cmd, keeping the same PID,
+// so signals reach the command directly. It only returns on error. Unlike
+// cmd.Run, execve keeps duplicated variables, and getenv returns the first
+// one, so the environment is deduplicated to keep the last value.
+func execCommand(cmd *exec.Cmd) error {
+	fp, err := exec.LookPath(cmd.Args[0])
+	if err != nil {
+		return err
+	}
+
+	return syscall.Exec(fp, cmd.Args, dedupEnv(cmd.Env))
+}
@@ -0,0 +1,91 @@
+// +build !windows
+
+package main
+
+import (
+	"io/ioutil"
+	"os"
+	"os/exec"
+	"syscall"
+	"testing"
+)
+
+func TestExecCommandKeepsLastValue(t *testing.T) {
+	// the replaced process is a copy of the test binary, which turns into
+	// printenv instead of running the tests again
+	if os.Getenv("DOTENV_TEST_EXEC") == "1" {
+		cmd := exec.Command("printenv", "FOO")
+		cmd.Env = []string{"PATH=" + os.Getenv("PATH"), "FOO=inherited", "FOO=from-env-file"}
+
+		if err := execCommand(cmd); err != nil {
+			t.Fatalf("execCommand() error = %v", err)
+		}
+
+		return
+	}
+
+	cmd := exec.Command(os.Args[0], "-test.run=^TestExecCommandKeepsLastValue$")
+	cmd.Env = append(os.Environ(), "DOTENV_TEST_EXEC=1")
+
+	out, err := cmd.Output()
+	if err != nil {
+		t.Fatalf("running the exec helper failed: %v", err)
+	}
+
+	if got, want := string(out), "from-env-file\n"; got != want {
+		t.Errorf("printenv FOO = %q, want %q", got, want)
+	}
+}
+
+func TestWriteEnvFd(t *testing.T) {
+	if fdFile(1) != os.Stdout || fdFile(2) != os.Stderr {
+		t.Errorf("fdFile() doesn't reuse os.Stdout and os.Stderr")
+	}
+
+	fds := make([]int, 2)
+	if err := syscall.Pipe(fds); err != nil {
+		t.Fatal(err)
+	}
+
+	r := os.NewFile(uintptr(fds[0]), "pipe")
+	defer r.Close()
+
+	if err := writeEnvFd(fds[1], []string{"A=1", "B=two words"}); err != nil {
+		t.Fatalf("writeEnvFd() error = %v", err)
+	}
+
+	// the write end is closed, so reading stops at the end of the output
+	got, err := ioutil.ReadAll(r)
+	if err != nil {
+		t.Fatal(err)
+	}
+
+	if want := "A=1\nB=two words\n"; string(got) != want {
+		t.Errorf("read %q from the file descriptor, want %q", got, want)
+	}
+}
+
+func TestExitCodeSignaled(t *testing.T) {
+	cases := []struct {
+		script string
+		want   int
+	}{
+		{script: "kill -TERM $$", want: 143},
+		{script: "kill -KILL $$", want: 137},
+		{script: "exit 3", want: 3},
+	}
+
+	for _, tc := range cases {
+		err := exec.Command("sh", "-c", tc.script).Run()
+
+		e, ok := err.(*exec.ExitError)
+		if !ok {
+			t.Errorf("sh -c %q error = %v, want an exit error", tc.script, err)
+			continue
+		}
+
+		if got := exitCode(e); got != tc.want {
+			t.Errorf("exitCode() after sh -c %q = %d, want %d", tc.script, got, tc.want)
+		}
+	}
+}
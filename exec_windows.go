@@ -0,0 +1,13 @@
+// +build windows
+
+package main
+
+import (
+	"os/exec"
+)
+
+// execCommand runs cmd as a child process, since Windows can't replace
+// the running process with another program.
+func execCommand(cmd *exec.Cmd) error {
+	return cmd.Run()
+}
@@ -0,0 +1,386 @@
+package main
+
+import (
+	"fmt"
+	"os"
+	"os/exec"
+	"strconv"
+	"strings"
+	"unicode/utf8"
+)
+
+// unquote removes the quotes surrounding a value, if any, returning the
+// quote character used or zero if the value wasn't quoted.
+func unquote(value string) (string, byte) {
+	if len(value) >= 2 {
+		if q := value[0]; (q == '"' || q == '\'' || q == '`') && value[len(value)-1] == q {
+			return value[1 : len(value)-1], q
+		}
+	}
+
+	return value, 0
+}
+
+// trimAfterQuote drops what follows the closing quote of a value that
+// starts with a quote but doesn't end with it: whitespace, then optionally
+// a comment starting with "#". Any other text is an error, since it's most
+// likely a misplaced quote. For double quotes, the closing one is the
+// first that isn't escaped with a backslash. Other values, and values whose
+// quote is never closed, are returned unchanged.
+func trimAfterQuote(value string) (string, error) {
+	if len(value) < 2 {
+		return value, nil
+	}
+
+	q := value[0]
+	if (q != '"' && q != '\'' && q != '`') || value[len(value)-1] == q {
+		return value, nil
+	}
+
+	end := -1
+	for i := 1; i < len(value); i++ {
+		if q == '"' && value[i] == '\\' {
+			i++
+			continue
+		}
+
+		if value[i] == q {
+			end = i
+			break
+		}
+	}
+
+	if end < 0 {
+		return value, nil
+	}
+
+	rest := value[end+1:]
+	trimmed := strings.TrimLeft(rest, " \t")
+
+	if trimmed == "" || (startswith(trimmed, "#") && len(trimmed) < len(rest)) {
+		return value[:end+1], nil
+	}
+
+	return "", fmt.Errorf("unexpected text %q after the closing quote", strings.TrimSpace(rest))
+}
+
+// decodeEscape decodes the escape sequence at the start of s, one of the
+// ones allowed in double-quoted values: \n, \r, \t, \\, \", \$, \xHH and
+// \uXXXX, the last two being the character with that hexadecimal code. It
+// returns the decoded text and how many bytes of s it used, which is zero
+// for malformed or unknown sequences, to be kept as written.
+func decodeEscape(s string) (string, int) {
+	if len(s) < 2 || s[0] != '\\' {
+		return "", 0
+	}
+
+	switch next := s[1]; next {
+	case 'n':
+		return "\n", 2
+
+	case 'r':
+		return "\r", 2
+
+	case 't':
+		return "\t", 2
+
+	case '\\', '"', '$':
+		return string(next), 2
+
+	case 'x', 'u':
+		digits := 2
+		if next == 'u' {
+			digits = 4
+		}
+
+		if r, ok := hexRune(s[2:], digits); ok {
+			return string(r), 2 + digits
+		}
+	}
+
+	return "", 0
+}
+
+// hexRune reads a character code written as exactly digits hexadecimal
+// digits at the start of s. It fails if there are fewer digits or the code
+// isn't a valid character, such as a lone UTF-16 surrogate.
+func hexRune(s string, digits int) (rune, bool) {
+	if len(s) < digits {
+		return 0, false
+	}
+
+	n, err := strconv.ParseUint(s[:digits], 16, 32)
+	if err != nil || !utf8.ValidRune(rune(n)) {
+		return 0, false
+	}
+
+	return rune(n), true
+}
+
+// expandValue replaces ${VAR} and $VAR references in value. Variables are
+// looked up first in vars, the ones parsed so far from the same file, and
+// then in the base environment. Unset variables expand to an empty string
+// with a warning. A backslash before a "$" keeps it literal. With escapes,
+// used for double-quoted values, escape sequences are decoded in the same
+// pass, so "\\$HOME" is a backslash followed by the value of HOME.
+func expandValue(value string, vars map[string]string, escapes bool) string {
+	var sb strings.Builder
+
+	for pos := 0; pos < len(value); pos++ {
+		c := value[pos]
+
+		if c == '\\' && pos+1 < len(value) && value[pos+1] == '$' {
+			sb.WriteByte('$')
+			pos++
+			continue
+		}
+
+		if c == '\\' && escapes {
+			if decoded, n := decodeEscape(value[pos:]); n > 0 {
+				sb.WriteString(decoded)
+				pos += n - 1
+				continue
+			}
+		}
+
+		if c != '$' {
+			sb.WriteByte(c)
+			continue
+		}
+
+		if pos+1 < len(value) && value[pos+1] == '{' {
+			closing := matchingBrace(value, pos+1)
+			if closing < 0 {
+				sb.WriteString(value[pos:])
+				break
+			}
+
+			sb.WriteString(expandBraces(value[pos+2:closing], vars, escapes))
+			pos = closing
+			continue
+		}
+
+		n := nameLength(value[pos+1:])
+		if n == 0 {
+			sb.WriteByte(c)
+			continue
+		}
+
+		sb.WriteString(lookupVar(value[pos+1:pos+1+n], vars))
+		pos += n
+	}
+
+	return sb.String()
+}
+
+// substituteCommands replaces every "$(command)" in value with the output
+// of running it with "sh -c", with surrounding whitespace trimmed, like
+// shells do. Commands get the base environment plus vars. Since the
+// result is expanded afterwards, any "$" in the output is escaped so it's
+// kept literal, and so is "\$(" in value. With escapes, backslashes in the
+// output are escaped too, so they aren't decoded.
+func substituteCommands(value string, vars map[string]string, escapes bool) (string, error) {
+	var sb strings.Builder
+
+	literal := strings.NewReplacer("$", `\$`)
+	if escapes {
+		literal = strings.NewReplacer(`\`, `\\`, "$", `\$`)
+	}
+
+	for pos := 0; pos < len(value); pos++ {
+		if value[pos] == '\\' && startswith(value[pos+1:], "$(") {
+			sb.WriteString(`\$(`)
+			pos += 2
+			continue
+		}
+
+		if !startswith(value[pos:], "$(") {
+			sb.WriteByte(value[pos])
+			continue
+		}
+
+		closing := matchingBrace(value, pos+1)
+		if closing < 0 {
+			return "", fmt.Errorf("missing closing parenthesis in %q", value[pos:])
+		}
+
+		command := value[pos+2 : closing]
+
+		cmd := exec.Command("sh", "-c", command)
+		cmd.Stderr = os.Stderr
+		cmd.Env = baseEnviron()
+		for k, v := range vars {
+			cmd.Env = append(cmd.Env, k+"="+v)
+		}
+
+		out, err := cmd.Output()
+		if err != nil {
+			return "", fmt.Errorf("command substitution $(%s) failed: %s", command, err.Error())
+		}
+
+		sb.WriteString(literal.Replace(strings.TrimSpace(string(out))))
+		pos = closing
+	}
+
+	return sb.String(), nil
+}
+
+// expandBraces resolves the expression inside "${...}": either a plain
+// variable name, or one followed by an operator. "${VAR:-default}" expands
+// to the default when VAR is unset or empty, while "${VAR:=default}" also
+// sets VAR to it so later lines can reference it. Defaults are expanded too,
+// so they can be nested. "${VAR:offset}" and "${VAR:offset:length}" expand
+// to part of the value; like in shells, a negative offset needs a space
+// before it, as in "${VAR: -2}", to tell it apart from a default.
+func expandBraces(expr string, vars map[string]string, escapes bool) string {
+	n := nameLength(expr)
+	name, op := expr[:n], expr[n:]
+
+	if n > 0 && len(op) >= 2 && op[0] == ':' && strings.IndexByte(" 0123456789", op[1]) >= 0 {
+		if spec, ok := substringSpec(op[1:]); ok {
+			return substring(lookupVar(name, vars), spec)
+		}
+	}
+
+	if n == 0 || len(op) < 2 || op[0] != ':' || (op[1] != '-' && op[1] != '=') {
+		return lookupVar(expr, vars)
+	}
+
+	if v, found := findVar(name, vars); found && v != "" {
+		return v
+	}
+
+	def := expandValue(op[2:], vars, escapes)
+	if op[1] == '=' {
+		vars[strings.ToUpper(name)] = def
+	}
+
+	return def
+}
+
+// substringSpec reads the "offset" or "offset:length" that follows the
+// variable name in "${VAR:offset:length}".
+func substringSpec(spec string) ([]int, bool) {
+	var out []int
+
+	for _, v := range strings.SplitN(spec, ":", 2) {
+		n, err := strconv.Atoi(strings.TrimSpace(v))
+		if err != nil {
+			return nil, false
+		}
+
+		out = append(out, n)
+	}
+
+	return out, true
+}
+
+// substring returns the characters of value selected by spec, an offset
+// and an optional length. Negative offsets count from the end, and so do
+// negative lengths, which mark where the result ends, like in bash.
+// Positions out of range are clamped, so the result is at worst empty.
+func substring(value string, spec []int) string {
+	runes := []rune(value)
+	start, end := clampIndex(spec[0], len(runes)), len(runes)
+
+	if len(spec) == 2 {
+		if length := spec[1]; length < 0 {
+			end = clampIndex(length, len(runes))
+		} else if start+length < end {
+			end = start + length
+		}
+	}
+
+	if end <= start {
+		return ""
+	}
+
+	return string(runes[start:end])
+}
+
+// clampIndex turns pos, which counts from the end when negative, into a
+// position between 0 and size.
+func clampIndex(pos, size int) int {
+	if pos < 0 {
+		pos += size
+	}
+
+	switch {
+	case pos < 0:
+		return 0
+
+	case pos > size:
+		return size
+	}
+
+	return pos
+}
+
+// matchingBrace returns the position of the "}" or ")" closing the "{" or
+// "(" found at open, taking nested ones into account, or -1 if it's never
+// closed.
+func matchingBrace(value string, open int) int {
+	closing := byte('}')
+	if value[open] == '(' {
+		closing = ')'
+	}
+
+	depth := 0
+
+	for pos := open; pos < len(value); pos++ {
+		switch value[pos] {
+		case value[open]:
+			depth++
+
+		case closing:
+			depth--
+			if depth == 0 {
+				return pos
+			}
+		}
+	}
+
+	return -1
+}
+
+// nameLength returns how many bytes at the start of s form a variable
+// name, that is, a letter or underscore followed by letters, digits or
+// underscores.
+func nameLength(s string) int {
+	for pos := 0; pos < len(s); pos++ {
+		c := s[pos]
+
+		switch {
+		case c == '_' || c >= 'a' && c <= 'z' || c >= 'A' && c <= 'Z':
+		case c >= '0' && c <= '9' && pos > 0:
+		default:
+			return pos
+		}
+	}
+
+	return len(s)
+}
+
+// unsetRefs collects the names of the variables referenced while unset,
+// for --error-on-unset to report.
+var unsetRefs []string
+
+// lookupVar returns the value of a referenced variable. Since keys in env
+// files are uppercased, references to them are case-insensitive.
+func lookupVar(name string, vars map[string]string) string {
+	if v, found := findVar(name, vars); found {
+		return v
+	}
+
+	unsetRefs = append(unsetRefs, name)
+	warnf("variable %q is not set, expanding it to an empty string", name)
+	return ""
+}
+
+func findVar(name string, vars map[string]string) (string, bool) {
+	if v, found := vars[strings.ToUpper(name)]; found {
+		return v, true
+	}
+
+	return lookupBase(name)
+}
@@ -0,0 +1,244 @@
+package main
+
+import (
+	"io/ioutil"
+	"os"
+	"os/exec"
+	"path/filepath"
+	"reflect"
+	"testing"
+)
+
+func TestExpandValue(t *testing.T) {
+	cases := []struct {
+		value   string
+		escapes bool
+		want    string
+	}{
+		{value: "hello $NAME", want: "hello world"},
+		{value: "${NAME}!", want: "world!"},
+		{value: "${name}", want: "world"},
+		{value: "cost $5 or $", want: "cost $5 or $"},
+		{value: "${unclosed", want: "${unclosed"},
+		{value: `\$NAME`, want: "$NAME"},
+		{value: `\$NAME`, escapes: true, want: "$NAME"},
+		{value: `\\$NAME`, want: `\$NAME`},
+		{value: `\\$NAME`, escapes: true, want: `\world`},
+		{value: `a\tb`, want: `a\tb`},
+		{value: `a\tb`, escapes: true, want: "a\tb"},
+		{value: `\u00e9\x41`, escapes: true, want: "éA"},
+		{value: `\x4 \q`, escapes: true, want: `\x4 \q`},
+		{value: "${EMPTY:-fallback}", want: "fallback"},
+		{value: "${NAME:-fallback}", want: "world"},
+		{value: "${DOTENV_TEST_UNSET:-${NAME}}", want: "world"},
+		{value: "${DOTENV_TEST_UNSET:=set}-$DOTENV_TEST_UNSET", want: "set-set"},
+		{value: "${ID:2:4}", want: "2345"},
+		{value: "${ID:7}", want: "789"},
+		{value: "${ID: -3}", want: "789"},
+		{value: "${ID: -3:2}", want: "78"},
+		{value: "${ID:2:-2}", want: "234567"},
+		{value: "${ID:-3}", want: "0123456789"},
+		{value: "${ID:8:10}", want: "89"},
+		{value: "${ID: -20:2}", want: "01"},
+		{value: "${ID:20}", want: ""},
+		{value: "${ID:6:-6}", want: ""},
+		{value: "${ACCENTS:1:2}", want: "éî"},
+	}
+
+	for _, tc := range cases {
+		vars := map[string]string{"NAME": "world", "EMPTY": "", "ID": "0123456789", "ACCENTS": "àéîõü"}
+
+		if got := expandValue(tc.value, vars, tc.escapes); got != tc.want {
+			t.Errorf("expandValue(%q, escapes: %v) = %q, want %q", tc.value, tc.escapes, got, tc.want)
+		}
+	}
+}
+
+func TestSubstituteCommands(t *testing.T) {
+	if _, err := exec.LookPath("sh"); err != nil {
+		t.Skip("no sh to run commands with")
+	}
+
+	cases := []struct {
+		value   string
+		escapes bool
+		want    string
+		wantErr bool
+	}{
+		{value: "$(echo hello)", want: "hello"},
+		{value: "[$(printf '  spaced  ')]", want: "[spaced]"},
+		{value: "$(echo $NAME)", want: "world"},
+		{value: "$(echo '$HOME')", want: `\$HOME`},
+		{value: `\$(echo hi)`, want: `\$(echo hi)`},
+		{value: `$(printf 'a\\b')`, escapes: true, want: `a\\b`},
+		{value: "no commands", want: "no commands"},
+		{value: "$(exit 3)", wantErr: true},
+		{value: "$(echo unclosed", wantErr: true},
+	}
+
+	for _, tc := range cases {
+		got, err := substituteCommands(tc.value, map[string]string{"NAME": "world"}, tc.escapes)
+		if (err != nil) != tc.wantErr {
+			t.Errorf("substituteCommands(%q) error = %v, want error: %v", tc.value, err, tc.wantErr)
+			continue
+		}
+
+		if got != tc.want {
+			t.Errorf("substituteCommands(%q) = %q, want %q", tc.value, got, tc.want)
+		}
+	}
+}
+
+func TestLoadVirtualEnvAllowSubst(t *testing.T) {
+	if _, err := exec.LookPath("sh"); err != nil {
+		t.Skip("no sh to run commands with")
+	}
+
+	dir, err := ioutil.TempDir("", "dotenv")
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer os.RemoveAll(dir)
+
+	fp := filepath.Join(dir, ".env")
+	if err := ioutil.WriteFile(fp, []byte("GREETING=\"$(echo hello) world\"\nLITERAL='$(echo hi)'\n"), 0644); err != nil {
+		t.Fatal(err)
+	}
+
+	want := map[string]string{"GREETING": "hello world", "LITERAL": "$(echo hi)"}
+	if got, err := loadVirtualEnv(fp, parseOptions{allowSubst: true}); err != nil || !reflect.DeepEqual(got, want) {
+		t.Errorf("loadVirtualEnv(allowSubst: true) = %v, %v, want %v", got, err, want)
+	}
+
+	want = map[string]string{"GREETING": "$(echo hello) world", "LITERAL": "$(echo hi)"}
+	if got, err := loadVirtualEnv(fp, parseOptions{}); err != nil || !reflect.DeepEqual(got, want) {
+		t.Errorf("loadVirtualEnv() = %v, %v, want %v", got, err, want)
+	}
+
+	if err := ioutil.WriteFile(fp, []byte("FAILS=$(exit 3)\n"), 0644); err != nil {
+		t.Fatal(err)
+	}
+
+	if _, err := loadVirtualEnv(fp, parseOptions{allowSubst: true}); err == nil {
+		t.Errorf("loadVirtualEnv(allowSubst: true) with a failing command succeeded, want an error")
+	}
+}
+
+func TestExpandBracesAssigns(t *testing.T) {
+	vars := map[string]string{"EMPTY": ""}
+
+	if got := expandBraces("empty:=default", vars, false); got != "default" {
+		t.Errorf("expandBraces() = %q, want %q", got, "default")
+	}
+
+	if vars["EMPTY"] != "default" {
+		t.Errorf("EMPTY = %q after :=, want %q", vars["EMPTY"], "default")
+	}
+}
+
+func TestUnquote(t *testing.T) {
+	cases := []struct {
+		value string
+		want  string
+		quote byte
+	}{
+		{value: `"`, want: `"`},
+		{value: `""`, want: "", quote: '"'},
+		{value: `''`, want: "", quote: '\''},
+		{value: "``", want: "", quote: '`'},
+		{value: `"\u00e9"`, want: `\u00e9`, quote: '"'},
+		{value: "\"\u00e9\"", want: "\u00e9", quote: '"'},
+		{value: "'\U0001F600'", want: "\U0001F600", quote: '\''},
+		{value: `"mismatched'`, want: `"mismatched'`},
+		{value: "plain", want: "plain"},
+	}
+
+	for _, tc := range cases {
+		got, quote := unquote(tc.value)
+		if got != tc.want || quote != tc.quote {
+			t.Errorf("unquote(%q) = %q, %q, want %q, %q", tc.value, got, quote, tc.want, tc.quote)
+		}
+	}
+}
+
+func TestDecodeEscape(t *testing.T) {
+	cases := []struct {
+		s    string
+		want string
+		n    int
+	}{
+		{s: `\n`, want: "\n", n: 2},
+		{s: `\r\n`, want: "\r", n: 2},
+		{s: `\\`, want: `\`, n: 2},
+		{s: `\"`, want: `"`, n: 2},
+		{s: `\$HOME`, want: "$", n: 2},
+		{s: `\x41rest`, want: "A", n: 4},
+		{s: `\u00e9`, want: "é", n: 6},
+		{s: `\ud800`, n: 0},
+		{s: `\xZZ`, n: 0},
+		{s: `\u12`, n: 0},
+		{s: `\q`, n: 0},
+		{s: `\`, n: 0},
+		{s: "n", n: 0},
+	}
+
+	for _, tc := range cases {
+		got, n := decodeEscape(tc.s)
+		if got != tc.want || n != tc.n {
+			t.Errorf("decodeEscape(%q) = %q, %d, want %q, %d", tc.s, got, n, tc.want, tc.n)
+		}
+	}
+}
+
+func TestHexRune(t *testing.T) {
+	cases := []struct {
+		s      string
+		digits int
+		want   rune
+		ok     bool
+	}{
+		{s: "41", digits: 2, want: 'A', ok: true},
+		{s: "00e9xyz", digits: 4, want: 'é', ok: true},
+		{s: "4", digits: 2},
+		{s: "zz", digits: 2},
+		{s: "D800", digits: 4},
+	}
+
+	for _, tc := range cases {
+		got, ok := hexRune(tc.s, tc.digits)
+		if got != tc.want || ok != tc.ok {
+			t.Errorf("hexRune(%q, %d) = %q, %v, want %q, %v", tc.s, tc.digits, got, ok, tc.want, tc.ok)
+		}
+	}
+}
+
+func TestTrimAfterQuote(t *testing.T) {
+	cases := []struct {
+		value   string
+		want    string
+		wantErr bool
+	}{
+		{value: "plain # not a comment", want: "plain # not a comment"},
+		{value: `"value" # comment`, want: `"value"`},
+		{value: `'a # b'	# comment`, want: `'a # b'`},
+		{value: "`x`   ", want: "`x`"},
+		{value: `"say \"hi\"" # comment`, want: `"say \"hi\""`},
+		{value: `'it's'`, want: `'it's'`},
+		{value: `"unclosed # comment`, want: `"unclosed # comment`},
+		{value: `"x" y # c`, wantErr: true},
+		{value: `"x"y`, wantErr: true},
+		{value: `"x"# c`, wantErr: true},
+	}
+
+	for _, tc := range cases {
+		got, err := trimAfterQuote(tc.value)
+		if (err != nil) != tc.wantErr {
+			t.Errorf("trimAfterQuote(%q) error = %v, want error: %v", tc.value, err, tc.wantErr)
+			continue
+		}
+
+		if got != tc.want {
+			t.Errorf("trimAfterQuote(%q) = %q, want %q", tc.value, got, tc.want)
+		}
+	}
+}
@@ -1,75 +1,230 @@
 package main
 
-import "os"
+import (
+	"fmt"
+	"strings"
+)
+
+// flagdef describes a flag dotenv understands, with all the names it
+// can be called with and whether it expects a value.
+type flagdef struct {
+	names    []string
+	hasValue bool
+}
 
-func isControlFlagSet(flag ...string) bool {
-	if len(os.Args) <= 1 {
-		return false
-	}
+var flagdefs = []flagdef{
+	{names: []string{"-h", "--help"}},
+	{names: []string{"-v", "--version"}},
+	{names: []string{"-e", "--environment"}, hasValue: true},
+	{names: []string{"-f", "--file"}, hasValue: true},
+	{names: []string{"--env-next-to-command"}},
+	{names: []string{"--from-cargo-toml"}},
+	{names: []string{"--require-file"}},
+	{names: []string{"--profile-ext"}, hasValue: true},
+	{names: []string{"--wait-for"}, hasValue: true},
+	{names: []string{"--wait-timeout"}, hasValue: true},
+	{names: []string{"--delay"}, hasValue: true},
+	{names: []string{"--strict"}},
+	{names: []string{"--no-rc"}},
+	{names: []string{"--chain"}},
+	{names: []string{"--exec"}},
+	{names: []string{"--print-command"}},
+	{names: []string{"--trace-exec"}},
+	{names: []string{"--mask-reveal"}, hasValue: true},
+	{names: []string{"--compat"}, hasValue: true},
+	{names: []string{"--error-on-duplicate"}},
+	{names: []string{"--error-on-unset"}},
+	{names: []string{"--strict-names"}},
+	{names: []string{"--warnings-format"}, hasValue: true},
+	{names: []string{"--format"}, hasValue: true},
+	{names: []string{"--max-vars"}, hasValue: true},
+	{names: []string{"--no-control-vars"}},
+	{names: []string{"--allow-file-values"}},
+	{names: []string{"--allow-subst"}},
+	{names: []string{"--netrc"}},
+	{names: []string{"--assume-strict-for"}, hasValue: true},
+	{names: []string{"--keep"}, hasValue: true},
+	{names: []string{"--env-passthrough"}, hasValue: true},
+	{names: []string{"--keep-category"}, hasValue: true},
+	{names: []string{"--keep-functions"}},
+	{names: []string{"--unset"}, hasValue: true},
+	{names: []string{"--preserve-path"}},
+	{names: []string{"--strict-report"}},
+	{names: []string{"--expect-stripped"}, hasValue: true},
+	{names: []string{"--no-override", "--merge-env-first"}},
+	{names: []string{"--fail-on-override"}},
+	{names: []string{"--allow-override"}, hasValue: true},
+	{names: []string{"--set-if-unset"}, hasValue: true},
+	{names: []string{"--schema"}, hasValue: true},
+	{names: []string{"--print-missing"}},
+	{names: []string{"--require"}, hasValue: true},
+	{names: []string{"--require-nonempty"}, hasValue: true},
+	{names: []string{"--require-defined"}, hasValue: true},
+	{names: []string{"--dry-run"}},
+	{names: []string{"--compact"}},
+	{names: []string{"--hash"}},
+	{names: []string{"--group-by-source"}},
+	{names: []string{"--show-templates"}},
+	{names: []string{"--into-file"}, hasValue: true},
+	{names: []string{"--with-export"}},
+	{names: []string{"--hash-all"}},
+	{names: []string{"--json"}},
+	{names: []string{"--json-multiline-as-array"}},
+	{names: []string{"--output"}, hasValue: true},
+	{names: []string{"--tsv-header"}},
+	{names: []string{"--self-env"}},
+	{names: []string{"--only-overrides"}},
+	{names: []string{"--show-types"}},
+	{names: []string{"--fd"}, hasValue: true},
+	{names: []string{"--quiet"}},
+	{names: []string{"--print-env-for"}, hasValue: true},
+	{names: []string{"--default-on-missing"}, hasValue: true},
+	{names: []string{"--no-trailing-newline"}},
+	{names: []string{"--print-keys-only"}},
+	{names: []string{"--inherit-from"}, hasValue: true},
+	{names: []string{"--export"}},
+	{names: []string{"--print-duplicates"}},
+	{names: []string{"--export-unset"}, hasValue: true},
+	{names: []string{"--each"}, hasValue: true},
+	{names: []string{"--concurrency"}, hasValue: true},
+	{names: []string{"--diff"}},
+
+	// hidden, checks that every output format can be read back
+	{names: []string{"--selftest"}},
+}
+
+// flagModifiers are the flags that only change what another flag does,
+// along with the flag each one requires, by any of its names.
+var flagModifiers = []struct {
+	name   string
+	parent string
+}{
+	{name: "--profile-ext", parent: "--environment"},
+	{name: "--require-file", parent: "--from-cargo-toml"},
+	{name: "--concurrency", parent: "--each"},
+	{name: "--allow-override", parent: "--fail-on-override"},
+	{name: "--expect-stripped", parent: "--strict-report"},
+	{name: "--print-missing", parent: "--schema"},
+	{name: "--compact", parent: "--dry-run"},
+	{name: "--hash", parent: "--dry-run"},
+	{name: "--group-by-source", parent: "--dry-run"},
+	{name: "--show-templates", parent: "--dry-run"},
+	{name: "--into-file", parent: "--dry-run"},
+	{name: "--with-export", parent: "--dry-run"},
+	{name: "--json-multiline-as-array", parent: "--json"},
+	{name: "--tsv-header", parent: "--output"},
+	{name: "--only-overrides", parent: "--self-env"},
+	{name: "--show-types", parent: "--self-env"},
+	{name: "--quiet", parent: "--self-env"},
+	{name: "--default-on-missing", parent: "--print-env-for"},
+	{name: "--no-trailing-newline", parent: "--print-env-for"},
+	{name: "--wait-timeout", parent: "--wait-for"},
+}
 
-	found := false
+type flagvalue struct {
+	name  string
+	value string
+}
 
-	for _, v := range flag {
-		if v == os.Args[1] {
-			found = true
-			break
-		}
+// cliflags holds the flags passed to dotenv, in the order they were given.
+type cliflags []flagvalue
 
-		if startswith(os.Args[1], v+"=") {
-			found = true
-			break
+func findFlag(name string) *flagdef {
+	for pos := range flagdefs {
+		for _, v := range flagdefs[pos].names {
+			if v == name {
+				return &flagdefs[pos]
+			}
 		}
 	}
 
-	return found
+	return nil
 }
 
-func getFlagValue(keys ...string) map[string]string {
-	out := make(map[string]string)
+// parseFlags reads dotenv's own flags from the beginning of args. Parsing
+// stops at "--" or at the first argument that isn't a known flag, which is
+// then considered the command to execute. Whatever comes after the flags
+// is returned as-is.
+func parseFlags(args []string) (cliflags, []string, error) {
+	var out cliflags
 
-	if len(keys) == 0 {
-		return out
-	}
+	for pos := 0; pos < len(args); pos++ {
+		arg := args[pos]
+
+		if arg == "--" {
+			return out, args[pos+1:], nil
+		}
+
+		name, value, inline := arg, "", false
+		if idx := strings.Index(arg, "="); startswith(arg, "-") && idx > 0 {
+			name, value, inline = arg[:idx], arg[idx+1:], true
+		}
+
+		def := findFlag(name)
+		if def == nil {
+			return out, args[pos:], nil
+		}
 
-	args := os.Args[1:]
-	for pos, arg := range args {
-		if len(arg) > 0 && arg[0] != '-' {
+		if !def.hasValue {
+			if inline {
+				return nil, nil, fmt.Errorf("flag %s does not take a value", name)
+			}
+
+			out = append(out, flagvalue{name: name})
 			continue
 		}
 
-		for _, name := range keys {
-			if prefix := name + "="; len(arg) >= len(prefix) && arg[:len(prefix)] == prefix {
-				key := arg[:len(prefix)-1]
-				value := arg[len(prefix):]
-				out[key] = value
-				continue
+		if !inline {
+			if pos+1 >= len(args) {
+				return nil, nil, fmt.Errorf("flag %s requires a value", name)
 			}
 
-			if arg == name {
-				if next := pos + 1; next < len(args) {
-					nextval := args[next]
+			pos++
+			value = args[pos]
+		}
+
+		out = append(out, flagvalue{name: name, value: value})
+	}
 
-					if nextval != "" && nextval[0] == '-' {
-						continue
-					}
+	return out, nil, nil
+}
 
-					out[name] = nextval
-					continue
-				}
+// isSet reports whether any of the given flag names was passed.
+func (f cliflags) isSet(names ...string) bool {
+	for _, v := range f {
+		for _, name := range names {
+			if v.name == name {
+				return true
 			}
 		}
 	}
 
-	return out
+	return false
 }
 
-func getAllArgsAfter(value string) []string {
-	args := os.Args[1:]
-	for pos, v := range args {
-		if len(v) >= len(value) && v[len(v)-len(value):] == value {
-			return append([]string{}, args[pos+1:]...)
+// value returns the value of the last occurrence of any of the given
+// flag names, or an empty string if none was passed.
+func (f cliflags) value(names ...string) string {
+	vals := f.values(names...)
+	if len(vals) == 0 {
+		return ""
+	}
+
+	return vals[len(vals)-1]
+}
+
+// values returns the values of every occurrence of any of the given
+// flag names, in the order they were passed.
+func (f cliflags) values(names ...string) []string {
+	var out []string
+
+	for _, v := range f {
+		for _, name := range names {
+			if v.name == name {
+				out = append(out, v.value)
+			}
 		}
 	}
 
-	return nil
+	return out
 }
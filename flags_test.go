@@ -0,0 +1,94 @@
+package main
+
+import (
+	"reflect"
+	"testing"
+)
+
+func TestParseFlags(t *testing.T) {
+	cases := []struct {
+		args    []string
+		flags   cliflags
+		rest    []string
+		wantErr bool
+	}{
+		{
+			args:  []string{"-e", "production", "ls", "-l"},
+			flags: cliflags{{name: "-e", value: "production"}},
+			rest:  []string{"ls", "-l"},
+		},
+		{
+			args:  []string{"--environment=production", "--strict", "--", "--help"},
+			flags: cliflags{{name: "--environment", value: "production"}, {name: "--strict"}},
+			rest:  []string{"--help"},
+		},
+		{
+			args:  []string{"-e=a=b", "--dry-run"},
+			flags: cliflags{{name: "-e", value: "a=b"}, {name: "--dry-run"}},
+		},
+		{
+			args:  []string{"-f", "--keep"},
+			flags: cliflags{{name: "-f", value: "--keep"}},
+		},
+		{
+			args: []string{"--unknown", "-e", "production"},
+			rest: []string{"--unknown", "-e", "production"},
+		},
+		{args: []string{"--strict=1"}, wantErr: true},
+		{args: []string{"--keep"}, wantErr: true},
+	}
+
+	for _, tc := range cases {
+		flags, rest, err := parseFlags(tc.args)
+		if (err != nil) != tc.wantErr {
+			t.Errorf("parseFlags(%q) error = %v, want error: %v", tc.args, err, tc.wantErr)
+			continue
+		}
+
+		if !reflect.DeepEqual(flags, tc.flags) || !reflect.DeepEqual(rest, tc.rest) {
+			t.Errorf("parseFlags(%q) = %v, %q, want %v, %q", tc.args, flags, rest, tc.flags, tc.rest)
+		}
+	}
+}
+
+func TestWithDefaults(t *testing.T) {
+	defaults := cliflags{
+		{name: "--strict"},
+		{name: "--keep", value: "TZ"},
+		{name: "--keep", value: "SSH_AUTH_SOCK"},
+		{name: "-e", value: "staging"},
+	}
+
+	cases := []struct {
+		flags cliflags
+		want  cliflags
+	}{
+		{
+			flags: cliflags{},
+			want:  defaults,
+		},
+		{
+			flags: cliflags{{name: "--environment", value: "production"}},
+			want: cliflags{
+				{name: "--strict"},
+				{name: "--keep", value: "TZ"},
+				{name: "--keep", value: "SSH_AUTH_SOCK"},
+				{name: "--environment", value: "production"},
+			},
+		},
+		{
+			flags: cliflags{{name: "--keep", value: "LANG"}, {name: "--strict"}},
+			want: cliflags{
+				{name: "-e", value: "staging"},
+				{name: "--keep", value: "LANG"},
+				{name: "--strict"},
+			},
+		},
+	}
+
+	for _, tc := range cases {
+		if got := tc.flags.withDefaults(defaults); !reflect.DeepEqual(got, tc.want) {
+			t.Errorf("%v.withDefaults(%v) = %v, want %v", tc.flags, defaults, got, tc.want)
+		}
+	}
+}
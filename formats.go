@@ -0,0 +1,112 @@
+package main
+
+import (
+	"bufio"
+	"bytes"
+	"encoding/json"
+	"fmt"
+	"strings"
+)
+
+const (
+	// formatJSONArray reads env files shaped as a JSON array of objects with
+	// "name" and "value" keys, as exported by many secret managers.
+	formatJSONArray = "json-array"
+
+	// formatINI reads classic INI files, where keys in a "[section]" are
+	// prefixed with the section name.
+	formatINI = "ini"
+)
+
+var formats = [...]string{formatJSONArray, formatINI}
+
+func parseJSONArray(data []byte) (map[string]string, error) {
+	var entries []json.RawMessage
+	if err := json.Unmarshal(data, &entries); err != nil {
+		return nil, fmt.Errorf("expected a JSON array of objects: %s", err.Error())
+	}
+
+	ev := make(map[string]string, len(entries))
+
+	for pos, raw := range entries {
+		var entry struct {
+			Name  *string `json:"name"`
+			Value *string `json:"value"`
+		}
+
+		if err := json.Unmarshal(raw, &entry); err != nil {
+			return nil, fmt.Errorf("entry %d is not an object with a string name and value: %s", pos, err.Error())
+		}
+
+		if entry.Name == nil || *entry.Name == "" || entry.Value == nil {
+			return nil, fmt.Errorf("entry %d is missing its name or value", pos)
+		}
+
+		ev[*entry.Name] = *entry.Value
+	}
+
+	return ev, nil
+}
+
+// parseINI reads an INI file, turning "key=value" under "[section]" into
+// SECTION_KEY=value. Keys before the first section or under "[DEFAULT]"
+// are used without prefix. Names are uppercased, with anything other than
+// letters, digits and underscores replaced by an underscore. Lines starting
+// with ";" or "#" are comments, and values may be wrapped in quotes.
+func parseINI(data []byte) (map[string]string, error) {
+	ev := make(map[string]string)
+	sc := bufio.NewScanner(bytes.NewReader(data))
+
+	prefix := ""
+	for line := 1; sc.Scan(); line++ {
+		text := strings.TrimSpace(sc.Text())
+		if text == "" || startswith(text, ";") || startswith(text, "#") {
+			continue
+		}
+
+		if startswith(text, "[") {
+			if !strings.HasSuffix(text, "]") {
+				return nil, fmt.Errorf("line %d: unclosed section header %q", line, text)
+			}
+
+			prefix = iniName(text[1 : len(text)-1])
+			if prefix == "DEFAULT" {
+				prefix = ""
+			}
+
+			continue
+		}
+
+		idx := strings.Index(text, "=")
+		if idx <= 0 {
+			return nil, fmt.Errorf("line %d: expected key=value, got %q", line, text)
+		}
+
+		key := iniName(text[:idx])
+		if prefix != "" {
+			key = prefix + "_" + key
+		}
+
+		value, _ := unquote(strings.TrimSpace(text[idx+1:]))
+		ev[key] = value
+	}
+
+	return ev, sc.Err()
+}
+
+// iniName turns an INI section or key name into an environment variable
+// name.
+func iniName(name string) string {
+	return strings.Map(func(r rune) rune {
+		switch {
+		case r >= 'a' && r <= 'z':
+			return r - 'a' + 'A'
+
+		case r >= 'A' && r <= 'Z', r >= '0' && r <= '9', r == '_':
+			return r
+
+		default:
+			return '_'
+		}
+	}, strings.TrimSpace(name))
+}
@@ -0,0 +1,91 @@
+package main
+
+import (
+	"reflect"
+	"testing"
+)
+
+func TestParseJSONArray(t *testing.T) {
+	data := `[
+  {"name": "HOST", "value": "localhost"},
+  {"name": "lower", "value": ""},
+  {"name": "HOST", "value": "example.com", "extra": true}
+]`
+
+	want := map[string]string{"HOST": "example.com", "lower": ""}
+
+	got, err := parseJSONArray([]byte(data))
+	if err != nil {
+		t.Fatalf("parseJSONArray() error = %v", err)
+	}
+
+	if !reflect.DeepEqual(got, want) {
+		t.Errorf("parseJSONArray() = %v, want %v", got, want)
+	}
+}
+
+func TestParseJSONArrayErrors(t *testing.T) {
+	cases := []string{
+		`{"name": "HOST", "value": "localhost"}`,
+		`[{"name": "HOST", "value": "localhost"}`,
+		`["HOST=localhost"]`,
+		`[{"name": "PORT", "value": 8080}]`,
+		`[{"name": "HOST"}]`,
+		`[{"name": "", "value": "x"}]`,
+		``,
+	}
+
+	for _, data := range cases {
+		if _, err := parseJSONArray([]byte(data)); err == nil {
+			t.Errorf("parseJSONArray(%q) succeeded, want an error", data)
+		}
+	}
+}
+
+func TestParseINI(t *testing.T) {
+	data := `; comment
+# another comment
+top=1
+
+[database]
+host = localhost
+Port="5432"
+
+[DEFAULT]
+debug=true
+
+[my-app.v2]
+display name='x y'
+`
+
+	want := map[string]string{
+		"TOP":                    "1",
+		"DATABASE_HOST":          "localhost",
+		"DATABASE_PORT":          "5432",
+		"DEBUG":                  "true",
+		"MY_APP_V2_DISPLAY_NAME": "x y",
+	}
+
+	got, err := parseINI([]byte(data))
+	if err != nil {
+		t.Fatalf("parseINI() error = %v", err)
+	}
+
+	if !reflect.DeepEqual(got, want) {
+		t.Errorf("parseINI() = %v, want %v", got, want)
+	}
+}
+
+func TestParseINIErrors(t *testing.T) {
+	cases := []string{
+		"[unclosed\nkey=value",
+		"[section]\nno separator",
+		"=value",
+	}
+
+	for _, data := range cases {
+		if _, err := parseINI([]byte(data)); err == nil {
+			t.Errorf("parseINI(%q) succeeded, want an error", data)
+		}
+	}
+}
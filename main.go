@@ -3,8 +3,12 @@ package main
 import (
 	"io/ioutil"
 	"log"
+	"net"
 	"os"
 	"os/exec"
+	"strconv"
+	"strings"
+	"time"
 )
 
 const (
@@ -17,16 +21,20 @@ var (
 	dotenvLocations = envOrDefault("DOTENV_FOLDER_PATH", "~/.dotenv/")
 	dotenvUse       = envOrDefault("DOTENV", "")
 	dotenvStrict    = envOrDefault(strictKey, "")
+	dotenvExt       = lookupEnvOr("DOTENV_PROFILE_EXT", ".env")
 	version         = "development"
 
-	knownDotenvVars = [...]string{"DOTENV_FOLDER_PATH", "DOTENV", debugKey, strictKey, aliasKey}
+	knownDotenvVars = [...]string{"DOTENV_FOLDER_PATH", "DOTENV", "DOTENV_PROFILE_EXT", debugKey, strictKey, aliasKey}
 )
 
-const usage = `Usage: dotenv [--environment | -e path] [command] [args...]
+const usage = `Usage: dotenv [flags] [--] [command] [args...]
 
 Place a ".env" file at the same level where the current working directory is,
 then execute dotenv [command] [args...].
 
+If there's also a ".env.local" file, it's loaded on top of ".env", overriding
+its values, and it's used on its own if there's no ".env" file.
+
 Additionally, use a ".env" file from ~/.dotenv/ or wherever $DOTENV_FOLDER_PATH
 points to, by specifying $DOTENV or --environment=filename or -e=filename (without
 the extension) and it will be used automatically. If the path passed is absolute,
@@ -47,9 +55,9 @@ arguments will be sent to the command. For example, the following call will exec
 	# don't need to specify it like "dotenv -e=kubectl kubectl get pods"
 
 If $DOTENV_STRICT is set to any value, and set either through environment variables
-or in the environment variables file, strict mode is applied, where the command
-gets executed only with the environment variables from the environment file, and
-without the environment variables from the environment. This mode is useful to not
+or in the environment variables file, or --strict is passed, strict mode is applied,
+where the command gets executed only with the environment variables from the
+environment file, and without the environment variables from the environment. This mode is useful to not
 leak environment variables to your commmands that don't really need them, but also
 keep in mind some programs rely on $PATH to be set, or $HOME or other useful
 environment variables.
@@ -60,7 +68,239 @@ A cool example with no arguments but configuration given via environment variabl
 	NAME=joe
 
 dotenv will execute your command, stdin, stdout and stderr will be piped, and the
-exit code will be passed to your terminal.`
+exit code will be passed to your terminal.
+
+With --chain, several commands can be run one after the other with the same
+environment by separating them with ":::". Like with "&&", dotenv stops at the
+first command that fails and exits with its code:
+
+	$ dotenv --chain go vet ./... ::: go test ./... ::: go build
+
+Flags are only read before the command; use "--" to stop reading flags early.
+
+Default flags can be written one per line, like "--keep TZ", in a ".dotenvrc"
+file in the current directory or, if there's none, in ~/.dotenvrc. Flags given
+in the command line replace the defaults for the same flag. --allow-subst,
+--allow-file-values, --inherit-from and git sources are only allowed in
+~/.dotenvrc, and --no-rc skips both files.
+
+Flags:
+
+	-e, --environment path   use the given file, or ~/.dotenv/<path>.env if present,
+	                         then $XDG_CONFIG_HOME/dotenv/<path>.env (by default
+	                         ~/.config/dotenv/<path>.env); "git:<ref>:<path>"
+	                         reads the file as it was at the given git revision;
+	                         can be repeated to layer environments, with later
+	                         ones overriding earlier ones and the ones that can't
+	                         be found skipped with a warning
+	-f, --file path          also load the given env file, on top of the ones from
+	                         --environment; can be repeated, with later files
+	                         overriding earlier ones; a glob pattern such as
+	                         "config/*.env" loads every match in name order, and
+	                         "-" reads the file from stdin
+	    --env-next-to-command
+	                         when no env file is given, use the ".env" file in the
+	                         same directory as the command's executable, found
+	                         through $PATH, falling back to the one in the current
+	                         directory
+	    --from-cargo-toml    also read variables from the [env] and
+	                         [package.metadata.dotenv] tables of the closest
+	                         Cargo.toml, overridden by any env file
+	    --require-file       with --from-cargo-toml, fail when there's no env file
+	                         instead of using only the variables from Cargo.toml
+	    --profile-ext ext    with --environment, the extension used to find files
+	                         in ~/.dotenv/ instead of ".env", can also be set
+	                         with $DOTENV_PROFILE_EXT; use an empty value, in
+	                         either of them, for files without extension
+	    --wait-for addr      wait until a TCP connection can be opened to the given
+	                         "host:port", which can use variables from the env
+	                         files like "${DB_HOST}:5432", before running the
+	                         command; can be repeated
+	    --wait-timeout duration
+	                         with --wait-for, give up after the given time, 30s by
+	                         default, exiting with code 124 without running the
+	                         command
+	    --delay duration     wait for the given time, like "500ms" or "2s", before
+	                         running the command
+	    --chain              run several commands separated with ":::", stopping at
+	                         the first one that fails; without it, ":::" is passed
+	                         to the command as any other argument
+	    --exec               replace the dotenv process with the command instead of
+	                         running it as a child, keeping the same PID; with
+	                         --chain, only the last command is replaced; on Windows
+	                         the command always runs as a child
+	    --print-command      print the command line that would run, after
+	                         resolving DOTENV_COMMAND, quoted for the shell,
+	                         instead of running it; one line per command with
+	                         --chain
+	    --strict             turn strict mode on, like setting $DOTENV_STRICT
+	    --no-rc              don't read default flags from .dotenvrc files
+	    --trace-exec         print the program, arguments, working directory and
+	                         environment (secrets masked) to stderr before running
+	    --mask-reveal n      show the first and last n characters of masked values,
+	                         unless the value has 2n characters or less
+	    --compat mode        parse the env file following another tool's rules;
+	                         "docker" matches "docker run --env-file": keys keep
+	                         their case, values are verbatim (no quote stripping),
+	                         empty values are kept, "#" only comments at line
+	                         start and a bare KEY is taken from the environment;
+	                         "python-dotenv" matches python-dotenv, see the README
+	    --error-on-duplicate fail if a key is set twice in the same env file, instead
+	                         of warning and using the last value
+	    --error-on-unset     fail if a value references a variable that isn't set,
+	                         instead of warning and expanding it to an empty string
+	    --strict-names       fail if a key in the env file isn't a valid variable
+	                         name (letters, digits and underscores, not starting
+	                         with a digit), instead of warning and using it
+	    --warnings-format fmt
+	                         write warnings to stderr as "text", the default, or
+	                         as "json", one object per line with a "code", the
+	                         "file", "line" and "key" when known, and a "message"
+	    --format fmt         read env files in another format instead of KEY=VALUE
+	                         lines; "json-array" reads a JSON array of objects with
+	                         "name" and "value" keys, as exported by secret managers,
+	                         and "ini" reads an INI file where keys in a [section]
+	                         become SECTION_KEY
+	    --max-vars n         fail if the env files set more than n variables
+	    --no-control-vars    don't interpret DOTENV_COMMAND and DOTENV_STRICT when
+	                         set in the env file, passing them to the command as is
+	    --allow-file-values  read values written as "@file:<path>" from the given
+	                         file, with surrounding whitespace trimmed
+	    --allow-subst        run the commands written as "$(command)" in unquoted
+	                         and double-quoted values with "sh -c", using their
+	                         trimmed output, like shells do
+	    --netrc              add the password from $NETRC or ~/.netrc to URL values
+	                         whose host has an entry there, unless they have one
+	    --assume-strict-for pattern
+	                         turn strict mode on when the working directory, or
+	                         any directory above it, matches the glob pattern;
+	                         can be repeated
+	    --keep key           in strict mode, keep the given variable from the
+	                         environment; can be repeated
+	    --env-passthrough pattern
+	                         in strict mode, keep the variables from the
+	                         environment whose name matches the glob pattern,
+	                         like "CI_*" or "*_TOKEN"; can be repeated
+	    --keep-category cat  in strict mode, keep the given family of variables
+	                         from the environment: "locale" (LANG, LANGUAGE, LC_*)
+	                         or "terminal" (TERM, COLORTERM, COLUMNS, ...); can be
+	                         repeated
+	    --keep-functions     in strict mode, keep the functions exported by bash
+	                         (BASH_FUNC_* variables) from the environment
+	    --unset key          remove the given variable from the environment the
+	                         command gets, whether inherited or set by the env
+	                         files; can be repeated
+	    --preserve-path      when both the env file and the environment set PATH,
+	                         prepend the env file's entries to the environment's
+	                         instead of replacing it, even in strict mode
+	    --no-override, --merge-env-first
+	                         ignore the variables from the env file that are
+	                         already set in the environment, so the environment
+	                         wins, unless strict mode is on
+	    --fail-on-override   fail if the env file sets a variable that's already
+	                         set in the environment, unless strict mode is on
+	    --allow-override key with --fail-on-override, allow the env file to
+	                         override the given variable; can be repeated
+	    --set-if-unset kv    set KEY=VALUE only if KEY isn't set by the env file or
+	                         the environment; can be repeated
+	    --strict-report      print the variables from the environment that the
+	                         command won't get, such as the ones removed by strict
+	                         mode, to stderr
+	    --expect-stripped pattern
+	                         with --strict-report, fail if a variable matching the
+	                         glob pattern is kept, or if a variable not matching
+	                         any pattern is removed; can be repeated
+	    --schema path        fail if a key defined in the given file (such as an
+	                         ".env.example") is missing from the environment
+	    --require key        fail if the given variable isn't set for the command,
+	                         even if empty; can be repeated
+	    --require-nonempty key
+	                         like --require, but also fail if the variable is set
+	                         to an empty value; can be repeated
+	    --require-defined key
+	                         like --require-nonempty, but checked before strict
+	                         mode, so a variable only set in the environment the
+	                         command won't get counts too; can be repeated
+	    --print-missing      with --schema, print the missing keys one per line
+	                         and exit 0 instead of failing and running the command
+	    --dry-run            print the variables set by the env files, sorted and
+	                         quoted so they can be read back, instead of running
+	                         the command
+	    --compact            with --dry-run, print all the variables in a single
+	                         line, separated by spaces and quoted for the shell
+	    --json               print the variables set by the env files as a JSON
+	                         object sorted by key instead of running the command
+	    --json-multiline-as-array
+	                         with --json, write values spanning several lines as
+	                         an array of their lines instead of a single string
+	    --hash               with --dry-run, print the SHA-256 hash of each value
+	                         instead of the value itself
+	    --group-by-source    with --dry-run, list the variables under a comment naming
+	                         the file that set their final value
+	    --show-templates     with --dry-run, print a "# KEY: template" comment with
+	                         the value as written before each interpolated one
+	    --into-file path     with --dry-run, write the output to the given file
+	                         instead of printing it
+	    --with-export        with --dry-run, start each variable with "export ", for
+	                         shells to source
+	    --hash-all           print a single SHA-256 hash of all the variables set
+	                         by the env files instead of running the command, to
+	                         use as a cache key
+	    --output fmt         print the variables set by the env files in the given
+	                         format instead of running the command; "properties"
+	                         writes a Java .properties file, "tsv" writes
+	                         tab-separated key and value columns, escaping tabs,
+	                         line breaks and backslashes, and "json-detailed"
+	                         writes a JSON array with the file and line that set
+	                         each variable
+	    --tsv-header         with --output tsv, start with a "key<TAB>value" row
+	    --self-env           print the full environment the command would get,
+	                         inherited variables included, instead of running it,
+	                         followed by a summary line on stderr; variables whose
+	                         name shells can't use get a trailing warning comment
+	    --only-overrides     with --self-env, print only the variables that are
+	                         new or whose value differs from the current environment
+	    --show-types         with --self-env, add a trailing comment with the kind
+	                         of value each variable seems to hold: bool, int,
+	                         float, url, json or string
+	    --fd n               write the environment the command gets, like --self-env,
+	                         to file descriptor n and then run the command as usual;
+	                         "--fd 2" writes it to stderr
+	    --print-env-for key  print the value the command would get for the given
+	                         variable, exiting 1 without output if it isn't set
+	    --default-on-missing str
+	                         with --print-env-for, print the given value and exit 0
+	                         when the variable isn't set; set but empty variables
+	                         always print an empty line
+	    --no-trailing-newline
+	                         with --print-env-for, print the value without a
+	                         trailing newline, like printf instead of echo
+	    --quiet              with --self-env, don't print the summary line it
+	                         writes to stderr
+	    --print-keys-only    like --self-env, but print only the sorted variable
+	                         names, without their values
+	    --inherit-from src   use another environment as the base instead of the
+	                         current one: "pid:<pid>" copies the environment of a
+	                         running process, "file:<path>" reads a snapshot with
+	                         one KEY=VALUE per line, like the output of "env"
+	    --export             print "export KEY='VALUE'" statements for the variables
+	                         set by the env files instead of running the command,
+	                         to load them in the current shell with eval
+	    --print-duplicates   print every variable set by more than one env file,
+	                         or by Cargo.toml, with the files that set it in the
+	                         order they're loaded, instead of running the command
+	    --export-unset shell print the statements that unset every variable set
+	                         by the env file in the given shell (bash, zsh, sh or
+	                         fish), to use with eval
+	    --each pattern       run the command once per env file matching the glob
+	                         pattern, each with its own environment; exits 1 if
+	                         any run fails
+	    --concurrency n      with --each, run up to n commands at the same time
+	    --diff a b           compare env files a and b instead of running a command,
+	                         printing added (+), removed (-) and changed (~) keys
+	                         with secrets masked; exits 1 if they differ
+	-h, --help               show this help
+	-v, --version            print the version and exit`
 
 func main() {
 	logger := log.New(ioutil.Discard, "[dotenv-debug] ", log.Lshortfile|log.LstdFlags)
@@ -69,88 +309,295 @@ func main() {
 		logger.SetOutput(os.Stdout)
 	}
 
-	var (
-		command string
-		evfile  string
-	)
+	var command string
 
-	args := os.Args[1:]
+	flags, args, err := parseFlags(os.Args[1:])
+	if err != nil {
+		logger.Printf("unable to parse flags: %s", err.Error())
+		errexit("%s, see --help", err.Error())
+	}
 
-	if isControlFlagSet("-h", "--help") {
+	if flags.isSet("-h", "--help") {
 		os.Stdout.WriteString(usage + "\n")
 		return
 	}
 
-	if isControlFlagSet("-v", "--version") {
+	if flags.isSet("-v", "--version") {
 		os.Stdout.WriteString("[dotenv] version " + version + "\n")
 		return
 	}
 
+	if flags.isSet("--selftest") {
+		os.Exit(runSelftest())
+	}
+
+	// modifiers are only checked when given in the command line, so
+	// default flags don't break the runs that don't use their parent
+	explicit := flags
+
+	if !flags.isSet("--no-rc") {
+		defaults, rcfile, err := loadRC()
+		if err != nil {
+			logger.Printf("unable to read default flags from %q: %s", rcfile, err.Error())
+			errexit("Can't read default flags: %s", err.Error())
+		}
+
+		if len(defaults) > 0 {
+			logger.Printf("read %d default flags from %q", len(defaults), rcfile)
+			flags = flags.withDefaults(defaults)
+		}
+	}
+
+	if flags.isSet("--strict") {
+		logger.Printf("strict mode turned on with --strict")
+		dotenvStrict = "1"
+	}
+
+	if flags.isSet("--warnings-format") {
+		switch format := flags.value("--warnings-format"); format {
+		case "text":
+		case "json":
+			warningsJSON = true
+		default:
+			logger.Printf("invalid --warnings-format value %q", format)
+			errexit("Invalid value %q for --warnings-format, expected text or json", format)
+		}
+	}
+
+	if flags.isSet("--mask-reveal") {
+		maskReveal, err = strconv.Atoi(flags.value("--mask-reveal"))
+		if err != nil || maskReveal < 0 {
+			logger.Printf("invalid --mask-reveal value %q", flags.value("--mask-reveal"))
+			errexit("Invalid value %q for --mask-reveal, expected a positive number", flags.value("--mask-reveal"))
+		}
+	}
+
+	for _, m := range flagModifiers {
+		if explicit.isSet(m.name) && !flags.isSet(findFlag(m.parent).names...) {
+			logger.Printf("exiting because %s was provided without %s", m.name, m.parent)
+			errexit("Flag %s requires %s, see --help", m.name, m.parent)
+		}
+	}
+
+	if flags.isSet("--each") {
+		// a default environment from the rc file is replaced by each
+		// file, so only an explicit one conflicts
+		if explicit.isSet("--environment", "-e") {
+			logger.Printf("exiting because --each was combined with --environment")
+			errexit("Flag --each can't be combined with --environment, see --help")
+		}
+
+		os.Exit(runEach(flags, explicit, args))
+	}
+
+	// some flags only report on the resolved environment, so there's
+	// no command to run when they're used
+	reportOnly := flags.isSet("--print-missing", "--self-env", "--print-keys-only", "--export-unset", "--print-env-for", "--output", "--dry-run", "--export", "--json", "--hash-all", "--print-duplicates")
+
 	if dotenvUse != "" {
-		logger.Printf("environment variable $DOTENV set to: %q -- using that as the file", dotenvUse)
-		evfile = dotenvUse
+		logger.Printf("environment variable $DOTENV set to: %q", dotenvUse)
 	}
 
-	if isControlFlagSet("--environment", "-e") {
-		vals := getFlagValue("--environment", "-e")
-		venv := ""
+	if flags.isSet("--profile-ext") {
+		dotenvExt = flags.value("--profile-ext")
+	}
+
+	opts := parseOptions{
+		fileValues:       flags.isSet("--allow-file-values"),
+		errorOnDuplicate: flags.isSet("--error-on-duplicate"),
+		errorOnUnset:     flags.isSet("--error-on-unset"),
+		strictNames:      flags.isSet("--strict-names"),
+		allowSubst:       flags.isSet("--allow-subst"),
+	}
 
-		logger.Printf("environment parameters parsed: %v", vals)
+	if flags.isSet("--netrc") {
+		fp := lookupEnvOr("NETRC", "~/.netrc")
 
-		if v, found := vals["--environment"]; found {
-			logger.Printf("long parameter --environment set to: %q", v)
-			venv = v
+		machines, err := loadNetrc(fp)
+		if err != nil {
+			logger.Printf("unable to read netrc file %q: %s", fp, err.Error())
+			errexit("Can't read netrc file %q: %s", fp, err.Error())
 		}
 
-		if v, found := vals["-e"]; found {
-			if venv != "" {
-				logger.Printf("exiting because both flags, --environment and -e were provided")
-				errexit("Both flags provided: --environment and -e -- must specify only one")
-			}
+		logger.Printf("adding credentials for %d hosts from %q to URL values", len(machines), fp)
+		opts.netrc = machines
+	}
+
+	if flags.isSet("--format") {
+		opts.format = flags.value("--format")
+		if !contains(formats[:], opts.format) {
+			logger.Printf("unknown env file format %q", opts.format)
+			errexit("Unknown env file format %q, supported formats: %s", opts.format, strings.Join(formats[:], ", "))
+		}
+
+		logger.Printf("reading env files as %q", opts.format)
+	}
+
+	if flags.isSet("--compat") {
+		opts.compat = flags.value("--compat")
+		if !contains(compatModes[:], opts.compat) {
+			logger.Printf("unknown compatibility mode %q", opts.compat)
+			errexit("Unknown compatibility mode %q, supported modes: %s", opts.compat, strings.Join(compatModes[:], ", "))
+		}
+
+		logger.Printf("parsing env file using %q compatibility mode", opts.compat)
+	}
 
-			logger.Printf("short parameter -e set to: %q", v)
-			venv = v
+	if flags.isSet("--diff") {
+		if len(args) != 2 {
+			logger.Printf("exiting because --diff got %d files instead of 2: %v", len(args), args)
+			errexit("Flag --diff requires exactly two files to compare, see --help")
 		}
 
-		if startswith(venv, "/") || startswith(venv, "./") {
-			logger.Printf("environment file passed %q starts with a control character, assuming full path", venv)
-			evfile = venv
-		} else {
-			if fp, found := envFilePresentInHome(venv); found {
-				logger.Printf("found a file in the user's directory with the file name matching %q: %s", venv, fp)
-				evfile = fp
-			} else {
-				logger.Printf("no file found in user's directory for %q, assuming full path", venv)
-				evfile = venv
+		files := make([]map[string]string, 0, len(args))
+		for _, fp := range args {
+			ev, err := loadVirtualEnv(fp, opts)
+			if err != nil {
+				logger.Printf("unable to read file %q to compare: %s", fp, err.Error())
+				errexit("Can't read environment variable file: %s", err.Error())
 			}
+
+			files = append(files, ev)
+		}
+
+		changes := diffEnv(files[0], files[1])
+		for _, v := range changes {
+			os.Stdout.WriteString(v + "\n")
+		}
+
+		if len(changes) > 0 {
+			os.Exit(1)
+		}
+
+		return
+	}
+
+	nextTo := ""
+	if flags.isSet("--env-next-to-command") && len(args) > 0 {
+		nextTo = args[0]
+	}
+
+	evfiles, err := envFiles(fileSources{
+		dotenv:       dotenvUse,
+		environments: flags.values("--environment", "-e"),
+		files:        flags.values("-f", "--file"),
+		ext:          dotenvExt,
+		nextTo:       nextTo,
+		cargo:        flags.isSet("--from-cargo-toml"),
+		requireFile:  flags.isSet("--require-file"),
+	})
+	if err != nil {
+		if e, ok := err.(*filenotfound); ok {
+			logger.Printf("no files match the pattern %q", e.name)
+			errexit("No dotenv file matches %q", e.name)
+		}
+
+		logger.Printf("unable to find the env files: %s", err.Error())
+		errexit("Can't read environment variable file: %s", err.Error())
+	}
+
+	// the base environment is seeded before parsing, since env files
+	// reference its variables
+	if flags.isSet("--inherit-from") {
+		source := flags.value("--inherit-from")
+
+		seeded, err := inheritFrom(source)
+		if err != nil {
+			logger.Printf("unable to seed environment from %q: %s", source, err.Error())
+			errexit("Can't inherit environment from %q: %s", source, err.Error())
 		}
 
-		args = getAllArgsAfter(venv)
-		logger.Printf("parsed arguments after environment flags to be: %#v", args)
+		logger.Printf("using %d variables from %q as the base environment", len(seeded), source)
+		inheritedEnv = append([]string{}, seeded...)
 	}
 
-	if evfile == "" {
-		logger.Printf("no env file set, defaulting to assuming there's one in the current directory")
-		evfile = ".env"
+	// stdin can be given by $DOTENV, --environment or --file, but it can
+	// only be read once
+	stdinFiles := 0
+	for _, fp := range evfiles {
+		if fp == stdinFile {
+			stdinFiles++
+		}
 	}
 
-	envvars, err := loadVirtualEnv(evfile)
+	if stdinFiles > 1 {
+		logger.Printf("exiting because stdin was given %d times as an env file", stdinFiles)
+		errexit("Env files can only be read from stdin (\"-\") once")
+	}
+
+	logger.Printf("env files to be loaded, in order: %v", evfiles)
+
+	// loaded holds every source of variables, in the order they're read
+	loaded := evfiles
+
+	origins := make(map[string]*origin)
+	opts.origins = origins
+
+	envvars, sources, err := loadEnvFiles(evfiles, opts)
 	if err != nil {
-		if _, ok := err.(*filenotfound); ok {
-			logger.Printf("unable to find dotenv file at %q", evfile)
-			errexit("No dotenv file found at %q", evfile)
+		if e, ok := err.(*filenotfound); ok {
+			logger.Printf("unable to find dotenv file at %q", e.name)
+			errexit("No dotenv file found at %q", e.name)
 		}
 
-		logger.Printf("unknown error while handling envfile %q: %s", evfile, err.Error())
+		logger.Printf("unknown error while handling env files %v: %s", evfiles, err.Error())
 		errexit("Can't read environment variable file: %s", err.Error())
 	}
 
-	aliascmd, hasalias := envvars[aliasKey]
+	if flags.isSet("--from-cargo-toml") {
+		cargovars, fp, err := loadCargoEnv()
+		if err != nil {
+			logger.Printf("unable to read variables from %s: %s", cargoManifest, err.Error())
+			errexit("Can't read variables from %s: %s", cargoManifest, err.Error())
+		}
+
+		logger.Printf("read %d variables from %q", len(cargovars), fp)
+		loaded = append([]string{fp}, loaded...)
+
+		// the manifest has the lowest precedence of all env files
+		for k, v := range cargovars {
+			if _, found := envvars[k]; !found {
+				envvars[k] = v
+				opts.record(k, fp, 0, "")
+			}
+
+			sources[k] = append([]string{fp}, sources[k]...)
+		}
+	}
+
+	if flags.isSet("--print-duplicates") {
+		for _, v := range duplicateKeys(sources) {
+			os.Stdout.WriteString(v + "\n")
+		}
+
+		return
+	}
+
+	if flags.isSet("--max-vars") {
+		limit, err := strconv.Atoi(flags.value("--max-vars"))
+		if err != nil || limit < 0 {
+			logger.Printf("invalid --max-vars value %q", flags.value("--max-vars"))
+			errexit("Invalid value %q for --max-vars, expected a positive number", flags.value("--max-vars"))
+		}
+
+		if len(envvars) > limit {
+			logger.Printf("exiting because env files set %d variables, more than the maximum of %d", len(envvars), limit)
+			errexit("Env files set %d variables, more than the maximum of %d allowed by --max-vars", len(envvars), limit)
+		}
+	}
+
+	if flags.isSet("--no-control-vars") {
+		logger.Printf("control variables disabled, passing %s and %s from the env file to the command", aliasKey, strictKey)
+	}
+
+	control := takeControlVars(envvars, flags.isSet("--no-control-vars"))
+	aliascmd, hasalias := control[aliasKey]
+
 	logger.Printf("found alias in env file? %v -- alias: %q", hasalias, aliascmd)
 
 	switch len(args) {
 	case 0:
-		if !hasalias {
+		if !hasalias && !reportOnly {
 			logger.Printf("exiting just because no alias was set and no commands were passed")
 			errexit("missing command and / or arguments, see --help")
 		}
@@ -172,18 +619,31 @@ func main() {
 		}
 
 		command = aliascmd
-		delete(envvars, aliasKey)
 
 		logger.Printf("swapping command due to alias to %q -- args: %#v", command, args)
 	}
 
-	if strict, found := envvars[strictKey]; found {
+	if strict, found := control[strictKey]; found {
+		logger.Printf("strict mode set by the env file to %q", strict)
 		dotenvStrict = strict
-		delete(envvars, strictKey)
 	}
 
-	environ := make([]string, 0, len(os.Environ()))
-	for _, v := range os.Environ() {
+	if patterns := flags.values("--assume-strict-for"); len(patterns) > 0 && dotenvStrict == "" {
+		matched, err := cwdMatches(patterns)
+		if err != nil {
+			logger.Printf("unable to match working directory against %v: %s", patterns, err.Error())
+			errexit("Can't check working directory for --assume-strict-for: %s", err.Error())
+		}
+
+		if matched {
+			logger.Printf("working directory matches %v, turning strict mode on", patterns)
+			dotenvStrict = "1"
+		}
+	}
+
+	base := baseEnviron()
+	environ := make([]string, 0, len(base))
+	for _, v := range base {
 		known := false
 		for _, m := range knownDotenvVars {
 			if startswith(v, m+"=") {
@@ -197,6 +657,114 @@ func main() {
 		}
 	}
 
+	parent := envMap(environ)
+
+	if flags.isSet("--no-override", "--merge-env-first") && dotenvStrict == "" {
+		for _, k := range sortedKeys(envvars) {
+			if _, found := parent[k]; found {
+				logger.Printf("keeping %q from the environment, ignoring the env file's value", k)
+				delete(envvars, k)
+			}
+		}
+	}
+
+	// in strict mode, only the variables matching these patterns are
+	// kept from the current environment
+	var keep []string
+	for _, c := range flags.values("--keep-category") {
+		patterns, found := keepCategories[c]
+		if !found {
+			logger.Printf("unknown category %q to keep", c)
+			errexit("Unknown category %q for --keep-category, supported categories: locale, terminal", c)
+		}
+
+		keep = append(keep, patterns...)
+	}
+
+	if flags.isSet("--keep-functions") {
+		keep = append(keep, "BASH_FUNC_*")
+	}
+
+	keep = append(keep, flags.values("--env-passthrough")...)
+
+	// the variables the command gets from the environment: all of them,
+	// or in strict mode only the kept ones
+	inherited := parent
+	if dotenvStrict != "" {
+		inherited = envMap(matchingVars(environ, flags.values("--keep"), keep))
+	}
+
+	defaulted, err := setIfUnset(envvars, inherited, flags.values("--set-if-unset"))
+	if err != nil {
+		logger.Printf("unable to apply --set-if-unset: %s", err.Error())
+		errexit("Can't apply the defaults: %s", err.Error())
+	}
+
+	logger.Printf("set to their defaults from --set-if-unset: %v", defaulted)
+
+	if flags.isSet("--preserve-path") {
+		filePath, inFile := envvars["PATH"]
+		parentPath, inParent := parent["PATH"]
+
+		if inFile && inParent && parentPath != "" {
+			envvars["PATH"] = filePath + string(os.PathListSeparator) + parentPath
+			logger.Printf("merged PATH from env file and environment: %q", envvars["PATH"])
+		}
+	}
+
+	if dotenvStrict == "" && flags.isSet("--fail-on-override") {
+		allowed := flags.values("--allow-override")
+
+		var overridden []string
+		for _, k := range sortedKeys(envvars) {
+			if _, found := parent[k]; found && !contains(allowed, k) {
+				overridden = append(overridden, k)
+			}
+		}
+
+		if len(overridden) > 0 {
+			logger.Printf("exiting because the env file overrides variables from the environment: %v", overridden)
+			errexit("Env file overrides variables already set in the environment: %s (use --allow-override to permit)", strings.Join(overridden, ", "))
+		}
+	}
+
+	if unset := flags.values("--unset"); len(unset) > 0 {
+		logger.Printf("removing variables from the environment: %v", unset)
+
+		kept := environ[:0]
+		for _, v := range environ {
+			if k, _ := splitEnvVar(v); !contains(unset, k) {
+				kept = append(kept, v)
+			}
+		}
+
+		environ = kept
+
+		for _, k := range unset {
+			delete(envvars, k)
+		}
+	}
+
+	if required := flags.values("--require-defined"); len(required) > 0 {
+		merged := append([]string{}, environ...)
+		for k, v := range envvars {
+			merged = append(merged, k+"="+v)
+		}
+
+		if missing := missingRequired(merged, required, true); len(missing) > 0 {
+			logger.Printf("exiting because required variables are undefined: %v", missing)
+			errexit("Required variables are undefined or empty: %s", strings.Join(missing, ", "))
+		}
+	}
+
+	// keys with an escaped "=" can be printed by the formats that escape
+	// them, but not passed to the command, which would read them back
+	// with a different name and value
+	if names := keysWithEquals(envvars); len(names) > 0 && !flags.isSet("--dry-run", "--json", "--output", "--hash-all") {
+		logger.Printf("exiting because the env file sets variables with \"=\" in their name: %v", names)
+		errexit("Variables with \"=\" in their name can't be passed to the command: %s", strings.Join(names, ", "))
+	}
+
 	vars := make([]string, 0, len(envvars)+len(environ))
 
 	logOffset := 0
@@ -204,6 +772,12 @@ func main() {
 		logger.Printf("strict mode environment variable not set: appending all current environment variables")
 		vars = append(vars, environ...)
 		logOffset = len(environ)
+	} else {
+		names := flags.values("--keep")
+		kept := matchingVars(environ, names, keep)
+		logger.Printf("strict mode: keeping %d variables from the environment named %v or matching %v", len(kept), names, keep)
+		vars = append(vars, kept...)
+		logOffset = len(kept)
 	}
 
 	for k, v := range envvars {
@@ -221,21 +795,355 @@ func main() {
 
 	logger.Printf("environment variables to be injected to command (besides %d current env vars): %v", len(environ), vars[logOffset:])
 
-	cmd := getCommand(command, args...)
-	cmd.Stdin = os.Stdin
-	cmd.Stdout = os.Stdout
-	cmd.Stderr = os.Stderr
-	cmd.Env = vars
+	if flags.isSet("--schema") {
+		schema := flags.value("--schema")
 
-	logger.Printf("command to be executed: %s %v", command, args)
+		missing, err := missingFromSchema(schema, vars)
+		if err != nil {
+			logger.Printf("unable to read schema file %q: %s", schema, err.Error())
+			errexit("Can't read schema file: %s", err.Error())
+		}
+
+		logger.Printf("variables in schema %q missing from the environment: %v", schema, missing)
+
+		if flags.isSet("--print-missing") {
+			for _, k := range missing {
+				os.Stdout.WriteString(k + "\n")
+			}
 
-	if err := cmd.Run(); err != nil {
-		if e, ok := err.(*exec.ExitError); ok {
-			logger.Printf("command exited with exit code: %v", e)
-			os.Exit(e.ExitCode())
+			return
 		}
 
-		logger.Printf("unable to execute command %q: %s", command, err.Error())
-		errexit("Unable to execute command %q: %s", command, err.Error())
+		if len(missing) > 0 {
+			errexit("Missing variables required by schema %q: %s", schema, strings.Join(missing, ", "))
+		}
+	}
+
+	if missing := missingRequired(vars, flags.values("--require"), false); len(missing) > 0 {
+		logger.Printf("exiting because required variables are missing: %v", missing)
+		errexit("Missing required variables: %s", strings.Join(missing, ", "))
+	}
+
+	if missing := missingRequired(vars, flags.values("--require-nonempty"), true); len(missing) > 0 {
+		logger.Printf("exiting because required variables are missing or empty: %v", missing)
+		errexit("Missing or empty required variables: %s", strings.Join(missing, ", "))
+	}
+
+	if flags.isSet("--strict-report") {
+		stripped, problems := strictReport(parent, vars, flags.values("--expect-stripped"))
+		os.Stderr.WriteString("[dotenv] removed " + strconv.Itoa(len(stripped)) + " variables from the environment: " + strings.Join(stripped, ", ") + "\n")
+
+		if len(problems) > 0 {
+			logger.Printf("exiting because strict mode didn't remove the expected variables: %v", problems)
+			errexit("Unexpected environment after strict mode: %s", strings.Join(problems, "; "))
+		}
+	}
+
+	if flags.isSet("--dry-run") {
+		printed := envvars
+		if flags.isSet("--hash") {
+			printed = hashValues(envvars)
+		}
+
+		if flags.isSet("--compact") && flags.isSet("--group-by-source") {
+			logger.Printf("exiting because --group-by-source was combined with --compact")
+			errexit("Flag --group-by-source can't be combined with --compact, see --help")
+		}
+
+		// templates can hold parts of the values, which --hash hides
+		if flags.isSet("--show-templates") && flags.isSet("--compact", "--hash") {
+			logger.Printf("exiting because --show-templates was combined with --compact or --hash")
+			errexit("Flag --show-templates can't be combined with --compact or --hash, see --help")
+		}
+
+		var templates map[string]string
+		if flags.isSet("--show-templates") {
+			templates = make(map[string]string)
+			for k, o := range origins {
+				if o.template != "" {
+					templates[k] = o.template
+				}
+			}
+		}
+
+		var lines []string
+		switch {
+		case flags.isSet("--compact"):
+			line, err := compactLine(printed)
+			if err != nil {
+				logger.Printf("unable to print variables in a single line: %s", err.Error())
+				errexit("Can't print variables in a single line: %s", err.Error())
+			}
+
+			lines = []string{line}
+
+		case flags.isSet("--group-by-source"):
+			lines = groupBySource(printed, sources, loaded, templates)
+
+		default:
+			lines = dotenvLines(printed, templates)
+		}
+
+		if flags.isSet("--with-export") {
+			exported, err := withExport(printed, lines)
+			if err != nil {
+				logger.Printf("unable to add export to the variables: %s", err.Error())
+				errexit("Can't add export to the variables: %s", err.Error())
+			}
+
+			lines = exported
+		}
+
+		if flags.isSet("--into-file") {
+			fp := flags.value("--into-file")
+			if err := writeLines(fp, lines); err != nil {
+				logger.Printf("unable to write variables to %q: %s", fp, err.Error())
+				errexit("Can't write variables to %q: %s", fp, err.Error())
+			}
+
+			logger.Printf("wrote %d variables to %q", len(printed), fp)
+			return
+		}
+
+		for _, v := range lines {
+			os.Stdout.WriteString(v + "\n")
+		}
+
+		return
+	}
+
+	if flags.isSet("--hash-all") {
+		os.Stdout.WriteString(hashAll(envvars) + "\n")
+		return
+	}
+
+	if flags.isSet("--export") {
+		lines, err := exportLines(envvars)
+		if err != nil {
+			logger.Printf("unable to generate export statements: %s", err.Error())
+			errexit("Can't generate export statements: %s", err.Error())
+		}
+
+		for _, v := range lines {
+			os.Stdout.WriteString(v + "\n")
+		}
+
+		return
+	}
+
+	if flags.isSet("--export-unset") {
+		shell := flags.value("--export-unset")
+
+		for _, k := range sortedKeys(envvars) {
+			stmt, err := unsetStatement(shell, k)
+			if err != nil {
+				logger.Printf("unable to generate unset statements: %s", err.Error())
+				errexit("Can't generate unset statements: %s", err.Error())
+			}
+
+			os.Stdout.WriteString(stmt + "\n")
+		}
+
+		return
+	}
+
+	if flags.isSet("--json") {
+		out, err := formatJSON(envvars, flags.isSet("--json-multiline-as-array"))
+		if err != nil {
+			logger.Printf("unable to print variables as JSON: %s", err.Error())
+			errexit("Can't print variables as JSON: %s", err.Error())
+		}
+
+		os.Stdout.WriteString(out)
+		return
+	}
+
+	if flags.value("--output") == outputJSONDetailed {
+		out, err := formatDetailedJSON(envvars, origins)
+		if err != nil {
+			logger.Printf("unable to print variables as JSON: %s", err.Error())
+			errexit("Can't print variables as JSON: %s", err.Error())
+		}
+
+		os.Stdout.WriteString(out)
+		return
+	}
+
+	if flags.isSet("--output") {
+		lines, err := formatOutput(flags.value("--output"), envvars)
+		if err != nil {
+			logger.Printf("unable to print variables: %s", err.Error())
+			errexit("Can't print variables: %s", err.Error())
+		}
+
+		if flags.value("--output") == outputTSV && flags.isSet("--tsv-header") {
+			lines = append([]string{"key\tvalue"}, lines...)
+		}
+
+		for _, v := range lines {
+			os.Stdout.WriteString(v + "\n")
+		}
+
+		return
+	}
+
+	if flags.isSet("--self-env") {
+		for _, v := range dedupEnv(vars) {
+			k, val := splitEnvVar(v)
+			if pv, found := parent[k]; found && pv == val && flags.isSet("--only-overrides") {
+				continue
+			}
+
+			var notes []string
+			if flags.isSet("--show-types") {
+				notes = append(notes, inferType(val))
+			}
+
+			if !validName(k) {
+				notes = append(notes, "warning: not a valid variable name for shells")
+			}
+
+			if len(notes) > 0 {
+				v += " # " + strings.Join(notes, ", ")
+			}
+
+			os.Stdout.WriteString(v + "\n")
+		}
+
+		if !flags.isSet("--quiet") {
+			os.Stderr.WriteString(summary(vars, envvars, parent, len(evfiles), dotenvStrict != "") + "\n")
+		}
+
+		return
+	}
+
+	if flags.isSet("--print-env-for") {
+		key := flags.value("--print-env-for")
+
+		value, found := envMap(vars)[key]
+		if !found {
+			if !flags.isSet("--default-on-missing") {
+				logger.Printf("variable %q is not set, exiting with status 1", key)
+				os.Exit(1)
+			}
+
+			value = flags.value("--default-on-missing")
+		}
+
+		if !flags.isSet("--no-trailing-newline") {
+			value += "\n"
+		}
+
+		os.Stdout.WriteString(value)
+		return
+	}
+
+	if flags.isSet("--print-keys-only") {
+		for _, v := range dedupEnv(vars) {
+			k, _ := splitEnvVar(v)
+			os.Stdout.WriteString(k + "\n")
+		}
+
+		return
+	}
+
+	if flags.isSet("--fd") {
+		fd, err := strconv.Atoi(flags.value("--fd"))
+		if err != nil || fd < 0 {
+			logger.Printf("invalid file descriptor %q", flags.value("--fd"))
+			errexit("Invalid file descriptor %q for --fd", flags.value("--fd"))
+		}
+
+		if err := writeEnvFd(fd, dedupEnv(vars)); err != nil {
+			logger.Printf("unable to write to file descriptor %d: %s", fd, err.Error())
+			errexit("Can't write environment to file descriptor %d: %s", fd, err.Error())
+		}
+	}
+
+	commands, err := commandList(append([]string{command}, args...), flags.isSet("--chain"))
+	if err != nil {
+		logger.Printf("unable to split commands: %s", err.Error())
+		errexit("%s, see --help", err.Error())
+	}
+
+	if flags.isSet("--print-command") {
+		for _, argv := range commands {
+			os.Stdout.WriteString(commandLine(argv) + "\n")
+		}
+
+		return
+	}
+
+	if addresses := flags.values("--wait-for"); len(addresses) > 0 {
+		timeout := 30 * time.Second
+		if flags.isSet("--wait-timeout") {
+			timeout, err = time.ParseDuration(flags.value("--wait-timeout"))
+			if err != nil || timeout <= 0 {
+				logger.Printf("invalid --wait-timeout value %q", flags.value("--wait-timeout"))
+				errexit("Invalid value %q for --wait-timeout, expected a duration like 30s or 2m", flags.value("--wait-timeout"))
+			}
+		}
+
+		// addresses can use the resolved environment, like "${DB_HOST}:5432"
+		env := envMap(vars)
+		for pos := range addresses {
+			addresses[pos] = expandValue(addresses[pos], env, false)
+
+			if _, _, err := net.SplitHostPort(addresses[pos]); err != nil {
+				logger.Printf("invalid --wait-for address %q: %s", addresses[pos], err.Error())
+				errexit("Invalid address %q for --wait-for, expected host:port", addresses[pos])
+			}
+		}
+
+		logger.Printf("waiting up to %s for %v to be reachable", timeout, addresses)
+
+		if err := waitFor(addresses, timeout); err != nil {
+			logger.Printf("exiting with code %d because waiting failed: %s", waitTimeoutCode, err.Error())
+			os.Stderr.WriteString("[dotenv] Gave up waiting: " + err.Error() + "\n")
+			os.Exit(waitTimeoutCode)
+		}
+	}
+
+	if flags.isSet("--delay") {
+		delay, err := time.ParseDuration(flags.value("--delay"))
+		if err != nil || delay < 0 {
+			logger.Printf("invalid --delay value %q", flags.value("--delay"))
+			errexit("Invalid value %q for --delay, expected a duration like 500ms or 2s", flags.value("--delay"))
+		}
+
+		logger.Printf("waiting %s before running the command", delay)
+		time.Sleep(delay)
+	}
+
+	for pos, argv := range commands {
+		command, args := argv[0], argv[1:]
+
+		cmd := getCommand(command, args...)
+		cmd.Stdin = os.Stdin
+		cmd.Stdout = os.Stdout
+		cmd.Stderr = os.Stderr
+		cmd.Env = vars
+
+		logger.Printf("command to be executed: %s %v", command, args)
+
+		if flags.isSet("--trace-exec") {
+			traceExec(os.Stderr, cmd, vars[logOffset:], logOffset)
+		}
+
+		run := cmd.Run
+		if flags.isSet("--exec") && pos == len(commands)-1 {
+			logger.Printf("replacing the dotenv process with %q", command)
+			run = func() error { return execCommand(cmd) }
+		}
+
+		if err := run(); err != nil {
+			if e, ok := err.(*exec.ExitError); ok {
+				logger.Printf("command exited with exit code: %v", e)
+				os.Exit(exitCode(e))
+			}
+
+			logger.Printf("unable to execute command %q: %s", command, err.Error())
+			errexit("Unable to execute command %q: %s", command, err.Error())
+		}
 	}
 }
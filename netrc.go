@@ -0,0 +1,131 @@
+package main
+
+import (
+	"fmt"
+	"net/url"
+	"strings"
+)
+
+// netrcEntry holds the credentials of a machine in a netrc file.
+type netrcEntry struct {
+	login    string
+	password string
+}
+
+// parseNetrc reads the "machine" entries of a netrc file, as used by curl
+// and ftp, keyed by host name. The "default" entry and macros are ignored,
+// so credentials only go to the hosts they were written for.
+func parseNetrc(data string) (map[string]netrcEntry, error) {
+	out := make(map[string]netrcEntry)
+
+	var (
+		machine string
+		entry   netrcEntry
+	)
+
+	flush := func() {
+		if machine != "" {
+			out[machine] = entry
+		}
+
+		machine, entry = "", netrcEntry{}
+	}
+
+	lines := strings.Split(strings.ReplaceAll(data, "\r\n", "\n"), "\n")
+	for pos := 0; pos < len(lines); pos++ {
+		tokens := strings.Fields(lines[pos])
+
+		for i := 0; i < len(tokens); i++ {
+			switch tokens[i] {
+			case "default":
+				flush()
+
+			case "macdef":
+				// a macro runs until the next empty line
+				flush()
+				for pos+1 < len(lines) && strings.TrimSpace(lines[pos+1]) != "" {
+					pos++
+				}
+
+				i = len(tokens)
+
+			case "machine", "login", "password", "account":
+				if i+1 >= len(tokens) {
+					return nil, fmt.Errorf("line %d: missing value for %q", pos+1, tokens[i])
+				}
+
+				value := tokens[i+1]
+				switch tokens[i] {
+				case "machine":
+					flush()
+					machine = value
+				case "login":
+					entry.login = value
+				case "password":
+					entry.password = value
+				}
+
+				i++
+
+			default:
+				if startswith(tokens[i], "#") {
+					i = len(tokens)
+					continue
+				}
+
+				return nil, fmt.Errorf("line %d: unknown token %q", pos+1, tokens[i])
+			}
+		}
+	}
+
+	flush()
+	return out, nil
+}
+
+// loadNetrc reads the netrc file at fp.
+func loadNetrc(fp string) (map[string]netrcEntry, error) {
+	fp, err := expand(fp)
+	if err != nil {
+		return nil, fmt.Errorf("unable to expand %q in path: %s", "~", err.Error())
+	}
+
+	data, err := loadFile(fp)
+	if err != nil {
+		return nil, err
+	}
+
+	return parseNetrc(data.String())
+}
+
+// withNetrcCredentials adds the credentials for the host of a URL value
+// from the netrc entries, if the URL has no password yet and its user, if
+// any, matches the entry's login. Other values are returned unchanged.
+func withNetrcCredentials(value string, machines map[string]netrcEntry) string {
+	u, err := url.Parse(value)
+	if err != nil || u.Scheme == "" || u.Host == "" {
+		return value
+	}
+
+	entry, found := machines[u.Hostname()]
+	if !found || entry.password == "" {
+		return value
+	}
+
+	login := entry.login
+	if u.User != nil {
+		if _, set := u.User.Password(); set {
+			return value
+		}
+
+		if name := u.User.Username(); name != "" {
+			if login != "" && name != login {
+				return value
+			}
+
+			login = name
+		}
+	}
+
+	u.User = url.UserPassword(login, entry.password)
+	return u.String()
+}
@@ -0,0 +1,382 @@
+package main
+
+import (
+	"bytes"
+	"crypto/sha256"
+	"encoding/hex"
+	"encoding/json"
+	"fmt"
+	"io/ioutil"
+	"sort"
+	"strings"
+	"unicode/utf16"
+)
+
+const (
+	// outputProperties prints variables as a Java ".properties" file.
+	outputProperties = "properties"
+
+	// outputTSV prints variables as tab-separated "key<TAB>value" lines.
+	outputTSV = "tsv"
+
+	// outputJSONDetailed prints variables as a JSON array, with the file
+	// and line each one was set by.
+	outputJSONDetailed = "json-detailed"
+)
+
+// formatOutput renders the variables set by dotenv, sorted by key, in the
+// given output format.
+func formatOutput(format string, vars map[string]string) ([]string, error) {
+	var out []string
+
+	for _, k := range sortedKeys(vars) {
+		switch format {
+		case outputProperties:
+			out = append(out, propertiesEscape(k, true)+"="+propertiesEscape(vars[k], false))
+
+		case outputTSV:
+			out = append(out, tsvEscape(k)+"\t"+tsvEscape(vars[k]))
+
+		default:
+			return nil, fmt.Errorf("unknown output format %q, supported formats: %s, %s, %s", format, outputProperties, outputTSV, outputJSONDetailed)
+		}
+	}
+
+	return out, nil
+}
+
+// detailedVar is a variable as printed by "--output json-detailed".
+type detailedVar struct {
+	Key    string `json:"key"`
+	Value  string `json:"value"`
+	Source string `json:"source"`
+	Line   int    `json:"line"`
+	Masked bool   `json:"masked"`
+}
+
+// formatDetailedJSON renders the variables as an indented JSON array, in
+// the order they were first set, with the file and line of their final
+// value. Variables without an origin, like defaults, come last sorted by
+// key with an empty source, and secrets are masked.
+func formatDetailedJSON(vars map[string]string, origins map[string]*origin) (string, error) {
+	keys := sortedKeys(vars)
+	sort.SliceStable(keys, func(i, j int) bool {
+		a, b := origins[keys[i]], origins[keys[j]]
+		if a == nil || b == nil {
+			return a != nil
+		}
+
+		return a.order < b.order
+	})
+
+	out := make([]detailedVar, 0, len(keys))
+	for _, k := range keys {
+		v := detailedVar{Key: k, Value: maskValue(k, vars[k]), Masked: isSecret(k)}
+		if o := origins[k]; o != nil {
+			v.Source, v.Line = o.file, o.line
+		}
+
+		out = append(out, v)
+	}
+
+	var buf bytes.Buffer
+
+	enc := json.NewEncoder(&buf)
+	enc.SetEscapeHTML(false)
+	enc.SetIndent("", "  ")
+
+	if err := enc.Encode(out); err != nil {
+		return "", err
+	}
+
+	return buf.String(), nil
+}
+
+// formatJSON renders the variables as an indented JSON object sorted by
+// key, with every value as a string. With multilineAsArray, values spanning
+// several lines are written as an array of their lines instead.
+func formatJSON(vars map[string]string, multilineAsArray bool) (string, error) {
+	var buf bytes.Buffer
+
+	enc := json.NewEncoder(&buf)
+	enc.SetEscapeHTML(false)
+	enc.SetIndent("", "  ")
+
+	out := make(map[string]interface{}, len(vars))
+	for k, v := range vars {
+		if multilineAsArray && strings.Contains(v, "\n") {
+			out[k] = strings.Split(v, "\n")
+			continue
+		}
+
+		out[k] = v
+	}
+
+	if err := enc.Encode(out); err != nil {
+		return "", err
+	}
+
+	return buf.String(), nil
+}
+
+// hashValues returns a copy of vars with every value replaced by its
+// hex-encoded SHA-256 hash, to detect changes without showing the values.
+func hashValues(vars map[string]string) map[string]string {
+	out := make(map[string]string, len(vars))
+
+	for k, v := range vars {
+		sum := sha256.Sum256([]byte(v))
+		out[k] = hex.EncodeToString(sum[:])
+	}
+
+	return out
+}
+
+// hashAll returns a single hex-encoded SHA-256 hash of every key and value
+// in vars, sorted by key. Keys and values are NUL-terminated so that moving
+// characters between them changes the hash.
+func hashAll(vars map[string]string) string {
+	h := sha256.New()
+
+	for _, k := range sortedKeys(vars) {
+		h.Write([]byte(k + "\x00" + vars[k] + "\x00"))
+	}
+
+	return hex.EncodeToString(h.Sum(nil))
+}
+
+// quoteValue quotes a value, if needed, so dotenv reads it back unchanged.
+// Single quotes are preferred since their content is taken literally, but
+// values spanning several lines use double quotes with escape sequences.
+func quoteValue(value string) string {
+	if value != "" && !strings.ContainsAny(value, " \t\n\r'\"\\$#`") {
+		return value
+	}
+
+	if !strings.ContainsAny(value, "\n\r") {
+		return "'" + value + "'"
+	}
+
+	r := strings.NewReplacer(`\`, `\\`, `"`, `\"`, "$", `\$`, "\n", `\n`, "\r", `\r`)
+	return `"` + r.Replace(value) + `"`
+}
+
+// shellQuote wraps s in single quotes for POSIX shells, where nothing is
+// special except the single quote itself, written as '\''.
+func shellQuote(s string) string {
+	return "'" + strings.ReplaceAll(s, "'", `'\''`) + "'"
+}
+
+// dotenvLines renders the variables, sorted by key, as KEY=value lines that
+// dotenv reads back unchanged, escaping any "=" in keys. Keys with a
+// template are preceded by a "# KEY: template" comment.
+func dotenvLines(vars map[string]string, templates map[string]string) []string {
+	lines := make([]string, 0, len(vars))
+
+	for _, k := range sortedKeys(vars) {
+		if t, found := templates[k]; found {
+			lines = append(lines, "# "+k+": "+t)
+		}
+
+		lines = append(lines, strings.ReplaceAll(k, "=", `\=`)+"="+quoteValue(vars[k]))
+	}
+
+	return lines
+}
+
+// groupBySource renders the variables like dotenvLines, grouped under a
+// "# <source>" comment for the source that set their final value, with the
+// sources in the order given. Variables without a source, like the defaults
+// from --set-if-unset, come last under "# defaults".
+func groupBySource(vars map[string]string, sources map[string][]string, order []string, templates map[string]string) []string {
+	groups := make(map[string]map[string]string)
+	for k, v := range vars {
+		source := "defaults"
+		if s := sources[k]; len(s) > 0 {
+			source = s[len(s)-1]
+		}
+
+		if groups[source] == nil {
+			groups[source] = make(map[string]string)
+		}
+
+		groups[source][k] = v
+	}
+
+	var out []string
+	for _, source := range append(append([]string{}, order...), "defaults") {
+		group, found := groups[source]
+		if !found {
+			continue
+		}
+
+		// a source given twice is only listed once
+		delete(groups, source)
+
+		if len(out) > 0 {
+			out = append(out, "")
+		}
+
+		out = append(out, "# "+source)
+		out = append(out, dotenvLines(group, templates)...)
+	}
+
+	return out
+}
+
+// withExport starts the KEY=value lines among the given ones with "export ",
+// leaving comments and blank lines alone. Like exportLines, it fails on keys
+// that aren't valid variable names, which shells can't export.
+func withExport(vars map[string]string, lines []string) ([]string, error) {
+	for _, k := range sortedKeys(vars) {
+		if !validName(k) {
+			return nil, fmt.Errorf("%q is not a valid variable name for shells", k)
+		}
+	}
+
+	out := make([]string, 0, len(lines))
+	for _, v := range lines {
+		if v != "" && !startswith(v, "#") {
+			v = "export " + v
+		}
+
+		out = append(out, v)
+	}
+
+	return out, nil
+}
+
+// writeLines writes the lines to the file at fp, replacing it. Since they
+// may hold secrets, a new file is only readable by its owner.
+func writeLines(fp string, lines []string) error {
+	var buf bytes.Buffer
+	for _, v := range lines {
+		buf.WriteString(v + "\n")
+	}
+
+	return ioutil.WriteFile(fp, buf.Bytes(), 0600)
+}
+
+// exportLines renders the variables, sorted by key, as POSIX shell export
+// statements. Keys are written as they are, so it fails on those that
+// aren't valid variable names instead of writing them into shell code.
+func exportLines(vars map[string]string) ([]string, error) {
+	lines := make([]string, 0, len(vars))
+
+	for _, k := range sortedKeys(vars) {
+		if !validName(k) {
+			return nil, fmt.Errorf("%q is not a valid variable name for shells", k)
+		}
+
+		lines = append(lines, "export "+k+"="+shellQuote(vars[k]))
+	}
+
+	return lines, nil
+}
+
+// compactLine renders the variables, sorted by key, as a single line of
+// space-separated KEY=value pairs quoted for the shell. Values spanning
+// several lines use $'...' quoting, so line breaks are written as "\n".
+// Like exportLines, it fails on keys that aren't valid variable names.
+func compactLine(vars map[string]string) (string, error) {
+	pairs := make([]string, 0, len(vars))
+
+	for _, k := range sortedKeys(vars) {
+		if !validName(k) {
+			return "", fmt.Errorf("%q is not a valid variable name for shells", k)
+		}
+
+		value := vars[k]
+
+		if strings.ContainsAny(value, "\n\r") {
+			r := strings.NewReplacer(`\`, `\\`, "'", `\'`, "\n", `\n`, "\r", `\r`)
+			pairs = append(pairs, k+"=$'"+r.Replace(value)+"'")
+			continue
+		}
+
+		pairs = append(pairs, k+"="+shellQuote(value))
+	}
+
+	return strings.Join(pairs, " "), nil
+}
+
+// commandLine joins the program and its arguments into a line that a POSIX
+// shell would split back into the same arguments, quoting only those that
+// need it.
+func commandLine(argv []string) string {
+	words := make([]string, 0, len(argv))
+
+	for _, arg := range argv {
+		safe := arg != ""
+		for _, r := range arg {
+			if !strings.ContainsRune("_@%+=:,./-", r) && !(r >= 'a' && r <= 'z' || r >= 'A' && r <= 'Z' || r >= '0' && r <= '9') {
+				safe = false
+				break
+			}
+		}
+
+		if safe {
+			words = append(words, arg)
+		} else {
+			words = append(words, shellQuote(arg))
+		}
+	}
+
+	return strings.Join(words, " ")
+}
+
+// tsvEscape escapes backslashes, tabs and line breaks so every variable
+// stays in a single row with exactly two columns.
+func tsvEscape(s string) string {
+	return strings.NewReplacer(`\`, `\\`, "\t", `\t`, "\n", `\n`, "\r", `\r`).Replace(s)
+}
+
+// propertiesEscape escapes s following the rules of Java's Properties.store:
+// backslashes, separators and comment characters are escaped, and so is any
+// character outside printable ASCII, written as \uXXXX. Spaces are escaped
+// everywhere in keys, but only at the start of values.
+func propertiesEscape(s string, isKey bool) string {
+	var sb strings.Builder
+
+	for pos, r := range s {
+		switch r {
+		case '\\':
+			sb.WriteString(`\\`)
+
+		case '\t':
+			sb.WriteString(`\t`)
+
+		case '\n':
+			sb.WriteString(`\n`)
+
+		case '\r':
+			sb.WriteString(`\r`)
+
+		case '\f':
+			sb.WriteString(`\f`)
+
+		case '=', ':', '#', '!':
+			sb.WriteByte('\\')
+			sb.WriteRune(r)
+
+		case ' ':
+			if isKey || pos == 0 {
+				sb.WriteByte('\\')
+			}
+
+			sb.WriteRune(r)
+
+		default:
+			if r >= 0x20 && r <= 0x7e {
+				sb.WriteRune(r)
+				continue
+			}
+
+			for _, u := range utf16.Encode([]rune{r}) {
+				fmt.Fprintf(&sb, `\u%04X`, u)
+			}
+		}
+	}
+
+	return sb.String()
+}
@@ -0,0 +1,416 @@
+package main
+
+import (
+	"encoding/json"
+	"io/ioutil"
+	"os"
+	"path/filepath"
+	"reflect"
+	"strings"
+	"testing"
+)
+
+func TestPropertiesEscape(t *testing.T) {
+	cases := []struct {
+		s     string
+		isKey bool
+		want  string
+	}{
+		{s: "key name", isKey: true, want: `key\ name`},
+		{s: "a=b:c", isKey: true, want: `a\=b\:c`},
+		{s: " leading and inner", want: `\ leading and inner`},
+		{s: `C:\dir`, want: `C\:\\dir`},
+		{s: "line1\nline2\ttab", want: `line1\nline2\ttab`},
+		{s: "#!", want: `\#\!`},
+		{s: "café", want: `caf\u00E9`},
+		{s: "😀", want: `\uD83D\uDE00`},
+	}
+
+	for _, tc := range cases {
+		if got := propertiesEscape(tc.s, tc.isKey); got != tc.want {
+			t.Errorf("propertiesEscape(%q, %v) = %q, want %q", tc.s, tc.isKey, got, tc.want)
+		}
+	}
+}
+
+func TestHashValues(t *testing.T) {
+	vars := map[string]string{"A": "abc", "EMPTY": ""}
+	want := map[string]string{
+		"A":     "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+		"EMPTY": "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+	}
+
+	if got := hashValues(vars); !reflect.DeepEqual(got, want) {
+		t.Errorf("hashValues() = %v, want %v", got, want)
+	}
+}
+
+func TestHashAll(t *testing.T) {
+	want := "3f504ae73e314b14d326f9f246c95d9d5e774e25c21b8a40e238c1f8b1486394"
+
+	// the hash doesn't depend on the order keys are set or iterated in
+	for i := 0; i < 10; i++ {
+		vars := map[string]string{"B": "two"}
+		vars["A"] = "1"
+
+		if got := hashAll(vars); got != want {
+			t.Fatalf("hashAll() = %q, want %q", got, want)
+		}
+	}
+
+	if hashAll(map[string]string{"A": "1", "B": "three"}) == want {
+		t.Errorf("hashAll() didn't change with a value")
+	}
+
+	if hashAll(map[string]string{"AB": "C"}) == hashAll(map[string]string{"A": "BC"}) {
+		t.Errorf("hashAll() is the same after moving characters between a key and its value")
+	}
+}
+
+func TestQuoteValue(t *testing.T) {
+	cases := []struct {
+		value string
+		want  string
+	}{
+		{value: "plain", want: "plain"},
+		{value: "", want: "''"},
+		{value: "two words", want: "'two words'"},
+		{value: "$HOME", want: "'$HOME'"},
+		{value: `back\slash`, want: `'back\slash'`},
+		{value: "a\nb", want: `"a\nb"`},
+		{value: "say \"hi\"\r\n$x\\", want: `"say \"hi\"\r\n\$x\\"`},
+	}
+
+	for _, tc := range cases {
+		if got := quoteValue(tc.value); got != tc.want {
+			t.Errorf("quoteValue(%q) = %q, want %q", tc.value, got, tc.want)
+		}
+	}
+}
+
+func TestDotenvLines(t *testing.T) {
+	vars := map[string]string{"B": "two words", "A": "plain", "C=D": "it's"}
+
+	want := []string{"A=plain", "B='two words'", `C\=D='it's'`}
+	if got := dotenvLines(vars, nil); !reflect.DeepEqual(got, want) {
+		t.Errorf("dotenvLines() = %q, want %q", got, want)
+	}
+}
+
+func TestShowTemplates(t *testing.T) {
+	dir, err := ioutil.TempDir("", "dotenv")
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer os.RemoveAll(dir)
+
+	fp := filepath.Join(dir, ".env")
+	data := "DB_HOST=db\nDB_PORT=5432\nDB_ADDR=\"${DB_HOST}:${DB_PORT}\"\nLITERAL='${DB_HOST}'\nCOST=$5\n"
+	if err := ioutil.WriteFile(fp, []byte(data), 0644); err != nil {
+		t.Fatal(err)
+	}
+
+	origins := make(map[string]*origin)
+	vars, err := loadVirtualEnv(fp, parseOptions{origins: origins})
+	if err != nil {
+		t.Fatalf("loadVirtualEnv() error = %v", err)
+	}
+
+	templates := make(map[string]string)
+	for k, o := range origins {
+		if o.template != "" {
+			templates[k] = o.template
+		}
+	}
+
+	want := []string{
+		"COST='$5'",
+		"# DB_ADDR: ${DB_HOST}:${DB_PORT}",
+		"DB_ADDR=db:5432",
+		"DB_HOST=db",
+		"DB_PORT=5432",
+		"LITERAL='${DB_HOST}'",
+	}
+
+	if got := dotenvLines(vars, templates); !reflect.DeepEqual(got, want) {
+		t.Errorf("dotenvLines() = %q, want %q", got, want)
+	}
+}
+
+func TestIntoFile(t *testing.T) {
+	dir, err := ioutil.TempDir("", "dotenv")
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer os.RemoveAll(dir)
+
+	in := filepath.Join(dir, "in.env")
+	out := filepath.Join(dir, "out.env")
+
+	data := "HOST=db.internal\nPORT=5432\nURL=\"postgres://${HOST}:${PORT}/app\"\nNAME=${DOTENV_TEST_UNSET:-app}\n"
+	if err := ioutil.WriteFile(in, []byte(data), 0644); err != nil {
+		t.Fatal(err)
+	}
+
+	vars, err := loadVirtualEnv(in, parseOptions{errorOnUnset: true})
+	if err != nil {
+		t.Fatalf("loadVirtualEnv() error = %v", err)
+	}
+
+	if err := writeLines(out, dotenvLines(vars, nil)); err != nil {
+		t.Fatalf("writeLines() error = %v", err)
+	}
+
+	written, err := ioutil.ReadFile(out)
+	if err != nil {
+		t.Fatal(err)
+	}
+
+	if strings.Contains(string(written), "${") {
+		t.Errorf("written file %q still has references", written)
+	}
+
+	want := map[string]string{"HOST": "db.internal", "PORT": "5432", "URL": "postgres://db.internal:5432/app", "NAME": "app"}
+	if got, err := loadVirtualEnv(out, parseOptions{}); err != nil || !reflect.DeepEqual(got, want) {
+		t.Errorf("loadVirtualEnv(written file) = %v, %v, want %v", got, err, want)
+	}
+}
+
+func TestErrorOnUnset(t *testing.T) {
+	warnOutput = ioutil.Discard
+	defer func() { warnOutput = os.Stderr }()
+
+	dir, err := ioutil.TempDir("", "dotenv")
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer os.RemoveAll(dir)
+
+	fp := filepath.Join(dir, ".env")
+	if err := ioutil.WriteFile(fp, []byte("URL=http://${DOTENV_TEST_UNSET}/\n"), 0644); err != nil {
+		t.Fatal(err)
+	}
+
+	if _, err := loadVirtualEnv(fp, parseOptions{errorOnUnset: true}); err == nil {
+		t.Errorf("loadVirtualEnv(errorOnUnset: true) with an unset reference succeeded, want an error")
+	}
+
+	if got, err := loadVirtualEnv(fp, parseOptions{}); err != nil || got["URL"] != "http:///" {
+		t.Errorf("loadVirtualEnv() = %v, %v, want URL expanded to %q", got, err, "http:///")
+	}
+}
+
+func TestWithExport(t *testing.T) {
+	dir, err := ioutil.TempDir("", "dotenv")
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer os.RemoveAll(dir)
+
+	fp := filepath.Join(dir, ".env")
+	if err := ioutil.WriteFile(fp, []byte("export HOST=localhost\nexport\tGREETING='hello world'\nPORT=8080\n"), 0644); err != nil {
+		t.Fatal(err)
+	}
+
+	vars, err := loadVirtualEnv(fp, parseOptions{})
+	if err != nil {
+		t.Fatalf("loadVirtualEnv() error = %v", err)
+	}
+
+	plain := dotenvLines(vars, nil)
+	if want := []string{"GREETING='hello world'", "HOST=localhost", "PORT=8080"}; !reflect.DeepEqual(plain, want) {
+		t.Errorf("dotenvLines() = %q, want %q", plain, want)
+	}
+
+	exported, err := withExport(vars, append([]string{"# .env", ""}, plain...))
+	if err != nil {
+		t.Fatalf("withExport() error = %v", err)
+	}
+
+	want := []string{"# .env", "", "export GREETING='hello world'", "export HOST=localhost", "export PORT=8080"}
+	if !reflect.DeepEqual(exported, want) {
+		t.Errorf("withExport() = %q, want %q", exported, want)
+	}
+
+	if err := writeLines(fp, exported); err != nil {
+		t.Fatalf("writeLines() error = %v", err)
+	}
+
+	if got, err := loadVirtualEnv(fp, parseOptions{}); err != nil || !reflect.DeepEqual(got, vars) {
+		t.Errorf("loadVirtualEnv(exported file) = %v, %v, want %v", got, err, vars)
+	}
+
+	bad := map[string]string{"A B": "x"}
+	if _, err := withExport(bad, dotenvLines(bad, nil)); err == nil {
+		t.Errorf("withExport(%q) succeeded, want an error", bad)
+	}
+}
+
+func TestExportLines(t *testing.T) {
+	cases := []struct {
+		vars    map[string]string
+		want    []string
+		wantErr bool
+	}{
+		{vars: map[string]string{}, want: []string{}},
+		{
+			vars: map[string]string{"B": "two words", "A": "it's", "C": "first\nsecond"},
+			want: []string{`export A='it'\''s'`, `export B='two words'`, "export C='first\nsecond'"},
+		},
+		{vars: map[string]string{"$(id)": "x"}, wantErr: true},
+		{vars: map[string]string{"A B": "x"}, wantErr: true},
+		{vars: map[string]string{"A=B": "x"}, wantErr: true},
+		{vars: map[string]string{"A\nB": "x"}, wantErr: true},
+	}
+
+	for _, tc := range cases {
+		got, err := exportLines(tc.vars)
+		if (err != nil) != tc.wantErr {
+			t.Errorf("exportLines(%q) error = %v, want error: %v", tc.vars, err, tc.wantErr)
+			continue
+		}
+
+		if !tc.wantErr && !reflect.DeepEqual(got, tc.want) {
+			t.Errorf("exportLines(%q) = %q, want %q", tc.vars, got, tc.want)
+		}
+	}
+}
+
+func TestCompactLine(t *testing.T) {
+	cases := []struct {
+		vars    map[string]string
+		want    string
+		wantErr bool
+	}{
+		{vars: map[string]string{}, want: ""},
+		{
+			vars: map[string]string{"B": "two words", "A": "plain", "C": "it's"},
+			want: `A='plain' B='two words' C='it'\''s'`,
+		},
+		{
+			vars: map[string]string{"KEY": "x\ny'z\\"},
+			want: `KEY=$'x\ny\'z\\'`,
+		},
+		{vars: map[string]string{"A B": "x"}, wantErr: true},
+		{vars: map[string]string{"$(id)": "x"}, wantErr: true},
+	}
+
+	for _, tc := range cases {
+		got, err := compactLine(tc.vars)
+		if (err != nil) != tc.wantErr {
+			t.Errorf("compactLine(%q) error = %v, want error: %v", tc.vars, err, tc.wantErr)
+			continue
+		}
+
+		if got != tc.want {
+			t.Errorf("compactLine(%q) = %q, want %q", tc.vars, got, tc.want)
+		}
+	}
+}
+
+func TestFormatJSON(t *testing.T) {
+	vars := map[string]string{"B": "first\nsecond", "A": "<one & two>"}
+
+	cases := []struct {
+		multilineAsArray bool
+		want             string
+	}{
+		{
+			multilineAsArray: false,
+			want:             "{\n  \"A\": \"<one & two>\",\n  \"B\": \"first\\nsecond\"\n}\n",
+		},
+		{
+			multilineAsArray: true,
+			want:             "{\n  \"A\": \"<one & two>\",\n  \"B\": [\n    \"first\",\n    \"second\"\n  ]\n}\n",
+		},
+	}
+
+	for _, tc := range cases {
+		got, err := formatJSON(vars, tc.multilineAsArray)
+		if err != nil {
+			t.Fatalf("formatJSON() error = %v", err)
+		}
+
+		if got != tc.want {
+			t.Errorf("formatJSON(multilineAsArray: %v) = %q, want %q", tc.multilineAsArray, got, tc.want)
+		}
+	}
+}
+
+func TestGroupBySource(t *testing.T) {
+	vars := map[string]string{"HOST": "localhost", "PORT": "8080", "DEBUG": "true", "NAME": "app", "TZ": "UTC"}
+	sources := map[string][]string{
+		"NAME":  {"Cargo.toml"},
+		"HOST":  {"Cargo.toml", ".env"},
+		"PORT":  {".env", ".env.local"},
+		"DEBUG": {".env.local"},
+	}
+
+	want := []string{
+		"# Cargo.toml",
+		"NAME=app",
+		"",
+		"# .env",
+		"HOST=localhost",
+		"",
+		"# .env.local",
+		"DEBUG=true",
+		"PORT=8080",
+		"",
+		"# defaults",
+		"TZ=UTC",
+	}
+
+	if got := groupBySource(vars, sources, []string{"Cargo.toml", ".env", ".env.local", ".env"}, nil); !reflect.DeepEqual(got, want) {
+		t.Errorf("groupBySource() = %q, want %q", got, want)
+	}
+}
+
+func TestFormatDetailedJSON(t *testing.T) {
+	dir, err := ioutil.TempDir("", "dotenv")
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer os.RemoveAll(dir)
+
+	base := filepath.Join(dir, ".env")
+	local := filepath.Join(dir, ".env.local")
+
+	if err := ioutil.WriteFile(base, []byte("HOST=localhost\nAPI_TOKEN=abcdef\nPORT=80\n"), 0644); err != nil {
+		t.Fatal(err)
+	}
+
+	if err := ioutil.WriteFile(local, []byte("# local overrides\nPORT=8080\nDEBUG=true\n"), 0644); err != nil {
+		t.Fatal(err)
+	}
+
+	origins := make(map[string]*origin)
+	vars, _, err := loadEnvFiles([]string{base, local}, parseOptions{origins: origins})
+	if err != nil {
+		t.Fatalf("loadEnvFiles() error = %v", err)
+	}
+
+	vars["TZ"] = "UTC"
+
+	out, err := formatDetailedJSON(vars, origins)
+	if err != nil {
+		t.Fatalf("formatDetailedJSON() error = %v", err)
+	}
+
+	var got []detailedVar
+	if err := json.Unmarshal([]byte(out), &got); err != nil {
+		t.Fatalf("formatDetailedJSON() printed invalid JSON %q: %v", out, err)
+	}
+
+	want := []detailedVar{
+		{Key: "HOST", Value: "localhost", Source: base, Line: 1},
+		{Key: "API_TOKEN", Value: "****", Source: base, Line: 2, Masked: true},
+		{Key: "PORT", Value: "8080", Source: local, Line: 2},
+		{Key: "DEBUG", Value: "true", Source: local, Line: 3},
+		{Key: "TZ", Value: "UTC"},
+	}
+
+	if !reflect.DeepEqual(got, want) {
+		t.Errorf("formatDetailedJSON() = %+v, want %+v", got, want)
+	}
+}
@@ -0,0 +1,127 @@
+package main
+
+import (
+	"bufio"
+	"fmt"
+	"path/filepath"
+	"strings"
+)
+
+// rcFiles are the places where default flags are read from, in order of
+// preference: only the first one found is used.
+var rcFiles = [...]string{".dotenvrc", "~/.dotenvrc"}
+
+// rcHomeOnly are the flags that make dotenv run commands, read sources
+// beyond the env files, reach the network or write the environment to a
+// file or where it may end up in a log. A ".dotenvrc" in the current directory may
+// come with any checked out repository, so they're only honoured in
+// ~/.dotenvrc.
+var rcHomeOnly = [...]string{
+	"--allow-subst", "--allow-file-values", "--inherit-from", "--netrc",
+	"--wait-for", "--fd", "--self-env", "--trace-exec", "--mask-reveal",
+	"--print-env-for", "--print-keys-only", "--into-file",
+}
+
+// homeOnly reports whether the flag can only be set from ~/.dotenvrc: the
+// ones in rcHomeOnly, env files read from git, and files outside of the
+// current directory.
+func homeOnly(f flagvalue) bool {
+	if contains(rcHomeOnly[:], f.name) {
+		return true
+	}
+
+	switch f.name {
+	case "-e", "--environment", "-f", "--file":
+		return startswith(f.value, gitPrefix) || outsideDir(f.value)
+	case "--schema":
+		return outsideDir(f.value)
+	}
+
+	return false
+}
+
+// outsideDir reports whether the path may point outside of the current
+// directory: absolute paths, paths in the home directory, and paths going
+// up with "..".
+func outsideDir(path string) bool {
+	if startswith(path, "~") || filepath.IsAbs(path) {
+		return true
+	}
+
+	clean := filepath.Clean(path)
+	return clean == ".." || startswith(clean, ".."+string(filepath.Separator))
+}
+
+// loadRC reads the default flags from the first rc file found. Each line
+// holds a single flag, written like in the command line, such as
+// "--keep TZ" or "-e=production", and lines starting with "#" are comments.
+// It returns the flags and the file they were read from, if any.
+func loadRC() (cliflags, string, error) {
+	for pos, name := range rcFiles {
+		fp, err := expand(name)
+		if err != nil {
+			continue
+		}
+
+		if !isFile(fp) {
+			continue
+		}
+
+		data, err := loadFile(fp)
+		if err != nil {
+			return nil, fp, err
+		}
+
+		var out cliflags
+		sc := bufio.NewScanner(data)
+
+		for line := 1; sc.Scan(); line++ {
+			text := strings.TrimSpace(sc.Text())
+			if text == "" || startswith(text, "#") {
+				continue
+			}
+
+			args := []string{text}
+			if idx := strings.IndexAny(text, " \t"); idx > 0 {
+				args = []string{text[:idx], strings.TrimSpace(text[idx+1:])}
+			}
+
+			flags, rest, err := parseFlags(args)
+			if err != nil {
+				return nil, fp, fmt.Errorf("%s:%d: %s", fp, line, err.Error())
+			}
+
+			if len(rest) > 0 {
+				return nil, fp, fmt.Errorf("%s:%d: unknown flag %q", fp, line, rest[0])
+			}
+
+			// only the last file, in the home directory, is trusted
+			for _, f := range flags {
+				if homeOnly(f) && pos < len(rcFiles)-1 {
+					return nil, fp, fmt.Errorf("%s:%d: %s %s is only allowed in %s", fp, line, f.name, f.value, rcFiles[len(rcFiles)-1])
+				}
+			}
+
+			out = append(out, flags...)
+		}
+
+		return out, fp, sc.Err()
+	}
+
+	return nil, "", nil
+}
+
+// withDefaults returns the flags with the given defaults added before them.
+// A default is dropped if the flag, by any of its names, was passed
+// explicitly, so repeatable flags are replaced rather than added to.
+func (f cliflags) withDefaults(defaults cliflags) cliflags {
+	out := make(cliflags, 0, len(defaults)+len(f))
+
+	for _, v := range defaults {
+		if def := findFlag(v.name); def != nil && !f.isSet(def.names...) {
+			out = append(out, v)
+		}
+	}
+
+	return append(out, f...)
+}
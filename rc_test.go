@@ -0,0 +1,33 @@
+package main
+
+import "testing"
+
+func TestHomeOnly(t *testing.T) {
+	cases := []struct {
+		flag flagvalue
+		want bool
+	}{
+		{flag: flagvalue{name: "--keep", value: "TZ"}, want: false},
+		{flag: flagvalue{name: "-e", value: "staging"}, want: false},
+		{flag: flagvalue{name: "-f", value: "config/local.env"}, want: false},
+		{flag: flagvalue{name: "-f", value: "..env"}, want: false},
+		{flag: flagvalue{name: "--schema", value: ".env.example"}, want: false},
+		{flag: flagvalue{name: "--allow-subst"}, want: true},
+		{flag: flagvalue{name: "--wait-for", value: "${GITHUB_TOKEN}.example.com:80"}, want: true},
+		{flag: flagvalue{name: "--fd", value: "3"}, want: true},
+		{flag: flagvalue{name: "--self-env"}, want: true},
+		{flag: flagvalue{name: "--trace-exec"}, want: true},
+		{flag: flagvalue{name: "--print-env-for", value: "AWS_SECRET_ACCESS_KEY"}, want: true},
+		{flag: flagvalue{name: "-e", value: "git:HEAD:.env"}, want: true},
+		{flag: flagvalue{name: "-f", value: "~/.aws/credentials"}, want: true},
+		{flag: flagvalue{name: "--file", value: "/etc/environment"}, want: true},
+		{flag: flagvalue{name: "-e", value: "../../.env"}, want: true},
+		{flag: flagvalue{name: "--schema", value: "config/../../secrets"}, want: true},
+	}
+
+	for _, tc := range cases {
+		if got := homeOnly(tc.flag); got != tc.want {
+			t.Errorf("homeOnly(%s %q) = %v, want %v", tc.flag.name, tc.flag.value, got, tc.want)
+		}
+	}
+}
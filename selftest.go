@@ -0,0 +1,331 @@
+package main
+
+import (
+	"encoding/json"
+	"fmt"
+	"io/ioutil"
+	"os"
+	"os/exec"
+	"strconv"
+	"strings"
+	"unicode/utf16"
+)
+
+// selftestValues are hard to write in every output format: empty values,
+// surrounding spaces, all kinds of quotes, backslashes, references, line
+// breaks and characters outside ASCII.
+var selftestValues = map[string]string{
+	"EMPTY":     "",
+	"SPACES":    "  leading and trailing  ",
+	"QUOTES":    "it's \"quoted\" and `ticked`",
+	"BACKSLASH": `C:\temp\new \\server\`,
+	"DOLLAR":    `$HOME ${PATH} $(id) \$`,
+	"COMMENT":   "value # not a comment",
+	"SEPARATOR": "a=b:c!d",
+	"TAB":       "a\tb",
+	"MULTILINE": "first\nsecond\r\nthird\n",
+	"MIXED":     "it's\n\"both\" \\ $x",
+	"UNICODE":   "café ü 😀 日本",
+}
+
+// selftestCheck writes the given variables with an output format and
+// reads them back.
+type selftestCheck struct {
+	name  string
+	shell string
+	run   func(vars map[string]string) (map[string]string, error)
+}
+
+var selftestChecks = []selftestCheck{
+	{name: "--dry-run", run: selftestDryRun},
+	{name: "--export", shell: "sh", run: selftestExport},
+	{name: "--dry-run --compact", shell: "bash", run: selftestCompact},
+	{name: "--output " + outputProperties, run: selftestProperties},
+	{name: "--output " + outputTSV, run: selftestTSV},
+	{name: "--json", run: selftestJSON},
+	{name: "--json --json-multiline-as-array", run: selftestJSONArray},
+}
+
+// runSelftest writes a set of tricky values with every output format and
+// reads them back, reporting the formats that don't give back the same
+// values. Checks needing a shell that isn't installed are skipped. It
+// returns the exit code to use: zero only if every check passed.
+func runSelftest() int {
+	code := 0
+
+	for _, check := range selftestChecks {
+		if check.shell != "" {
+			if _, err := exec.LookPath(check.shell); err != nil {
+				fmt.Printf("[dotenv] selftest: %s: skipped, %s not found\n", check.name, check.shell)
+				continue
+			}
+		}
+
+		got, err := check.run(selftestValues)
+		if err == nil {
+			err = sameVars(selftestValues, got)
+		}
+
+		if err != nil {
+			fmt.Printf("[dotenv] selftest: %s: FAIL: %s\n", check.name, err.Error())
+			code = 1
+			continue
+		}
+
+		fmt.Printf("[dotenv] selftest: %s: ok\n", check.name)
+	}
+
+	return code
+}
+
+// sameVars returns an error describing the first difference between the
+// variables written and the ones read back, if any.
+func sameVars(want, got map[string]string) error {
+	for _, k := range sortedKeys(want) {
+		v, found := got[k]
+		if !found {
+			return fmt.Errorf("%s is missing", k)
+		}
+
+		if v != want[k] {
+			return fmt.Errorf("%s is %q, expected %q", k, v, want[k])
+		}
+	}
+
+	for _, k := range sortedKeys(got) {
+		if _, found := want[k]; !found {
+			return fmt.Errorf("unexpected variable %s", k)
+		}
+	}
+
+	return nil
+}
+
+// selftestDryRun reads the output of --dry-run back as an env file.
+func selftestDryRun(vars map[string]string) (map[string]string, error) {
+	f, err := ioutil.TempFile("", "dotenv-selftest")
+	if err != nil {
+		return nil, err
+	}
+	defer os.Remove(f.Name())
+
+	for _, v := range dotenvLines(vars, nil) {
+		f.WriteString(v + "\n")
+	}
+
+	if err := f.Close(); err != nil {
+		return nil, err
+	}
+
+	return loadVirtualEnv(f.Name(), parseOptions{})
+}
+
+// selftestExport evaluates the output of --export with sh.
+func selftestExport(vars map[string]string) (map[string]string, error) {
+	lines, err := exportLines(vars)
+	if err != nil {
+		return nil, err
+	}
+
+	return shellValues("sh", strings.Join(lines, "\n"), sortedKeys(vars))
+}
+
+// selftestCompact evaluates the output of --dry-run --compact with bash,
+// since it uses $'...' quoting.
+func selftestCompact(vars map[string]string) (map[string]string, error) {
+	line, err := compactLine(vars)
+	if err != nil {
+		return nil, err
+	}
+
+	return shellValues("bash", "export "+line, sortedKeys(vars))
+}
+
+// shellValues runs script with the given shell and returns the values it
+// set for keys.
+func shellValues(shell, script string, keys []string) (map[string]string, error) {
+	printf := "printf '%s\\0'"
+	for _, k := range keys {
+		printf += ` "$` + k + `"`
+	}
+
+	out, err := exec.Command(shell, "-c", script+"\n"+printf).Output()
+	if err != nil {
+		return nil, fmt.Errorf("%s failed: %s", shell, err.Error())
+	}
+
+	values := strings.Split(string(out), "\x00")
+	if len(values) != len(keys)+1 {
+		return nil, fmt.Errorf("%s printed %d values, expected %d", shell, len(values)-1, len(keys))
+	}
+
+	got := make(map[string]string, len(keys))
+	for pos, k := range keys {
+		got[k] = values[pos]
+	}
+
+	return got, nil
+}
+
+// selftestProperties reads the output of --output properties back.
+func selftestProperties(vars map[string]string) (map[string]string, error) {
+	lines, err := formatOutput(outputProperties, vars)
+	if err != nil {
+		return nil, err
+	}
+
+	got := make(map[string]string, len(lines))
+	for _, line := range lines {
+		sep := -1
+		for pos := 0; pos < len(line); pos++ {
+			if line[pos] == '\\' {
+				pos++
+				continue
+			}
+
+			if line[pos] == '=' {
+				sep = pos
+				break
+			}
+		}
+
+		if sep < 0 {
+			return nil, fmt.Errorf("no separator in line %q", line)
+		}
+
+		got[propertiesUnescape(line[:sep])] = propertiesUnescape(line[sep+1:])
+	}
+
+	return got, nil
+}
+
+// propertiesUnescape reverses propertiesEscape, joining the UTF-16 surrogate
+// pairs written as two \uXXXX escapes.
+func propertiesUnescape(s string) string {
+	var (
+		sb    strings.Builder
+		units []uint16
+	)
+
+	flush := func() {
+		sb.WriteString(string(utf16.Decode(units)))
+		units = nil
+	}
+
+	for pos := 0; pos < len(s); pos++ {
+		if s[pos] != '\\' || pos+1 == len(s) {
+			flush()
+			sb.WriteByte(s[pos])
+			continue
+		}
+
+		pos++
+
+		if s[pos] == 'u' && pos+5 <= len(s) {
+			if n, err := strconv.ParseUint(s[pos+1:pos+5], 16, 16); err == nil {
+				units = append(units, uint16(n))
+				pos += 4
+				continue
+			}
+		}
+
+		flush()
+
+		switch s[pos] {
+		case 't':
+			sb.WriteByte('\t')
+
+		case 'n':
+			sb.WriteByte('\n')
+
+		case 'r':
+			sb.WriteByte('\r')
+
+		case 'f':
+			sb.WriteByte('\f')
+
+		default:
+			sb.WriteByte(s[pos])
+		}
+	}
+
+	flush()
+	return sb.String()
+}
+
+// selftestTSV reads the output of --output tsv back.
+func selftestTSV(vars map[string]string) (map[string]string, error) {
+	lines, err := formatOutput(outputTSV, vars)
+	if err != nil {
+		return nil, err
+	}
+
+	unescape := strings.NewReplacer(`\\`, `\`, `\t`, "\t", `\n`, "\n", `\r`, "\r")
+
+	got := make(map[string]string, len(lines))
+	for _, line := range lines {
+		cols := strings.Split(line, "\t")
+		if len(cols) != 2 {
+			return nil, fmt.Errorf("line %q has %d columns, expected 2", line, len(cols))
+		}
+
+		got[unescape.Replace(cols[0])] = unescape.Replace(cols[1])
+	}
+
+	return got, nil
+}
+
+// selftestJSON reads the output of --json back.
+func selftestJSON(vars map[string]string) (map[string]string, error) {
+	out, err := formatJSON(vars, false)
+	if err != nil {
+		return nil, err
+	}
+
+	var got map[string]string
+	if err := json.Unmarshal([]byte(out), &got); err != nil {
+		return nil, err
+	}
+
+	return got, nil
+}
+
+// selftestJSONArray reads the output of --json --json-multiline-as-array
+// back, joining the lines of values written as arrays.
+func selftestJSONArray(vars map[string]string) (map[string]string, error) {
+	out, err := formatJSON(vars, true)
+	if err != nil {
+		return nil, err
+	}
+
+	var decoded map[string]interface{}
+	if err := json.Unmarshal([]byte(out), &decoded); err != nil {
+		return nil, err
+	}
+
+	got := make(map[string]string, len(decoded))
+	for k, v := range decoded {
+		switch v := v.(type) {
+		case string:
+			got[k] = v
+
+		case []interface{}:
+			lines := make([]string, 0, len(v))
+			for _, line := range v {
+				s, ok := line.(string)
+				if !ok {
+					return nil, fmt.Errorf("%s has a line that isn't a string", k)
+				}
+
+				lines = append(lines, s)
+			}
+
+			got[k] = strings.Join(lines, "\n")
+
+		default:
+			return nil, fmt.Errorf("%s is neither a string nor an array", k)
+		}
+	}
+
+	return got, nil
+}
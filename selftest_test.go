@@ -0,0 +1,17 @@
+package main
+
+import "testing"
+
+func TestSelftest(t *testing.T) {
+	if code := runSelftest(); code != 0 {
+		t.Errorf("runSelftest() = %d, want 0", code)
+	}
+}
+
+func TestPropertiesUnescape(t *testing.T) {
+	for _, s := range []string{"key name", " a=b:c#!", `C:\dir`, "line\n\ttab\r\f", "café 😀"} {
+		if got := propertiesUnescape(propertiesEscape(s, false)); got != s {
+			t.Errorf("propertiesUnescape(propertiesEscape(%q)) = %q", s, got)
+		}
+	}
+}
@@ -3,14 +3,36 @@ package main
 import (
 	"bufio"
 	"bytes"
+	"encoding/json"
 	"fmt"
 	"io"
+	"io/ioutil"
+	"net/url"
 	"os"
+	"os/exec"
 	"os/user"
 	"path/filepath"
+	"sort"
+	"strconv"
 	"strings"
+	"syscall"
 )
 
+// keepCategories are the families of variables that can be kept from the
+// environment in strict mode, as patterns for filepath.Match.
+var keepCategories = map[string][]string{
+	"locale":   {"LANG", "LANGUAGE", "LC_*"},
+	"terminal": {"TERM", "TERM_PROGRAM", "COLORTERM", "TERMINFO", "TERMINFO_DIRS", "COLUMNS", "LINES"},
+}
+
+// secretMarkers are substrings that, when found in a variable name, cause
+// its value to be masked whenever dotenv prints it.
+var secretMarkers = [...]string{"SECRET", "TOKEN", "PASSWORD", "PASSWD", "PRIVATE", "CREDENTIAL", "API_KEY", "APIKEY"}
+
+// maskReveal is how many characters at each end of a masked value are kept
+// visible, set with --mask-reveal.
+var maskReveal int
+
 type filenotfound struct {
 	name string
 }
@@ -60,72 +82,1392 @@ func expand(path string) (string, error) {
 	return filepath.Join(usr.HomeDir, path[1:]), nil
 }
 
-func loadVirtualEnv(fp string) (map[string]string, error) {
+// loadEnvFiles reads every env file in order, merging them into a single
+// set of variables where files later in the list override earlier ones.
+// It also returns the files that set each variable, in the same order.
+func loadEnvFiles(files []string, opts parseOptions) (map[string]string, map[string][]string, error) {
+	out := make(map[string]string)
+	sources := make(map[string][]string)
+
+	for _, fp := range files {
+		ev, err := loadVirtualEnv(fp, opts)
+		if err != nil {
+			return nil, nil, err
+		}
+
+		for k, v := range ev {
+			out[k] = v
+			sources[k] = append(sources[k], fp)
+		}
+	}
+
+	return out, sources, nil
+}
+
+// duplicateKeys returns a "KEY: source, source" line, sorted by key, for
+// every variable set by more than one source.
+func duplicateKeys(sources map[string][]string) []string {
+	var out []string
+
+	for k, v := range sources {
+		if len(v) > 1 {
+			out = append(out, k+": "+strings.Join(v, ", "))
+		}
+	}
+
+	sort.Strings(out)
+	return out
+}
+
+// expandFileGlobs replaces the paths containing glob metacharacters with
+// the files they match, in lexical order. Like a missing file, a pattern
+// that matches nothing is an error.
+func expandFileGlobs(files []string) ([]string, error) {
+	out := make([]string, 0, len(files))
+
+	for _, fp := range files {
+		if startswith(fp, gitPrefix) || !strings.ContainsAny(fp, "*?[") {
+			out = append(out, fp)
+			continue
+		}
+
+		pattern, err := expand(fp)
+		if err != nil {
+			return nil, fmt.Errorf("unable to expand %q in path: %s", "~", err.Error())
+		}
+
+		matches, err := filepath.Glob(pattern)
+		if err != nil {
+			return nil, fmt.Errorf("invalid pattern %q: %s", fp, err.Error())
+		}
+
+		if len(matches) == 0 {
+			return nil, &filenotfound{name: fp}
+		}
+
+		out = append(out, matches...)
+	}
+
+	return out, nil
+}
+
+// utf8BOM is the byte order mark some editors add at the start of files.
+var utf8BOM = []byte("\xef\xbb\xbf")
+
+// stdinFile is the env file name that reads the variables from stdin.
+const stdinFile = "-"
+
+func loadStdin() (*bytes.Buffer, error) {
+	var buf bytes.Buffer
+	if _, err := io.Copy(&buf, os.Stdin); err != nil {
+		return nil, fmt.Errorf("unable to read from stdin: %s", err.Error())
+	}
+
+	return &buf, nil
+}
+
+// gitPrefix marks env file paths to be read from a git revision, like
+// "git:HEAD~3:.env".
+const gitPrefix = "git:"
+
+// loadGitFile reads a file as it was at a given git revision by running
+// "git show", with spec being "<ref>:<path>". As with "git show", paths are
+// relative to the repository root unless they start with "./".
+func loadGitFile(spec string) (*bytes.Buffer, error) {
+	if pos := strings.Index(spec, ":"); pos <= 0 || pos == len(spec)-1 {
+		return nil, fmt.Errorf("invalid git file %q, expected %s<ref>:<path>", spec, gitPrefix)
+	}
+
+	// a ref starting with "-" would be read by git as an option
+	if startswith(spec, "-") {
+		return nil, fmt.Errorf("invalid git ref in %q, refs can't start with %q", spec, "-")
+	}
+
+	var stdout, stderr bytes.Buffer
+
+	cmd := exec.Command("git", "show", spec)
+	cmd.Stdout = &stdout
+	cmd.Stderr = &stderr
+
+	if err := cmd.Run(); err != nil {
+		if msg := strings.TrimSpace(stderr.String()); msg != "" {
+			return nil, fmt.Errorf("unable to read %q from git: %s", spec, msg)
+		}
+
+		return nil, fmt.Errorf("unable to read %q from git: %s", spec, err.Error())
+	}
+
+	return &stdout, nil
+}
+
+const (
+	// compatDocker makes the parser follow the rules of "docker run --env-file".
+	compatDocker = "docker"
+
+	// compatPython makes the parser follow the rules of python-dotenv.
+	compatPython = "python-dotenv"
+)
+
+var compatModes = [...]string{compatDocker, compatPython}
+
+// parseOptions tweaks how env files are read.
+type parseOptions struct {
+	compat           string
+	format           string
+	fileValues       bool
+	errorOnDuplicate bool
+	errorOnUnset     bool
+	strictNames      bool
+	allowSubst       bool
+
+	// netrc holds the credentials added to URL values, by host
+	netrc map[string]netrcEntry
+
+	// origins, when set, is filled with where every variable read was
+	// set, by key
+	origins map[string]*origin
+
+	// including holds the files whose "include" directives led to the
+	// one being read, to catch cycles
+	including []string
+}
+
+// origin is the file and line that set a variable's final value, and the
+// position among all variables read where it was first set. Template is
+// the value as written, if references in it were replaced.
+type origin struct {
+	file     string
+	line     int
+	order    int
+	template string
+}
+
+// record notes that key was set by the given line of fp, keeping the
+// position where it was first set. Line is 0 for formats without lines,
+// and template is empty for values that weren't interpolated.
+func (opts parseOptions) record(key, fp string, line int, template string) {
+	if opts.origins == nil {
+		return
+	}
+
+	if o, found := opts.origins[key]; found {
+		o.file, o.line, o.template = fp, line, template
+		return
+	}
+
+	opts.origins[key] = &origin{file: fp, line: line, order: len(opts.origins), template: template}
+}
+
+// fileValuePrefix marks values that should be read from a file, like
+// "KEY=@file:/run/secrets/key", when file values are allowed.
+const fileValuePrefix = "@file:"
+
+func loadVirtualEnv(fp string, opts parseOptions) (map[string]string, error) {
 	if fp == "" {
 		return nil, nil
 	}
 
-	fp, err := expand(fp)
-	if err != nil {
-		return nil, fmt.Errorf("unable to expand %q in path: %s", "~", err.Error())
+	var (
+		data *bytes.Buffer
+		err  error
+	)
+
+	switch {
+	case fp == stdinFile:
+		data, err = loadStdin()
+
+	case startswith(fp, gitPrefix):
+		data, err = loadGitFile(fp[len(gitPrefix):])
+
+	default:
+		fp, err = expand(fp)
+		if err != nil {
+			return nil, fmt.Errorf("unable to expand %q in path: %s", "~", err.Error())
+		}
+
+		data, err = loadFile(fp)
 	}
 
-	data, err := loadFile(fp)
 	if err != nil {
 		return nil, err
 	}
 
+	// editors on Windows may start files with a byte order mark, which
+	// would otherwise end up in the first key
+	data = bytes.NewBuffer(bytes.TrimPrefix(data.Bytes(), utf8BOM))
+
+	if opts.format != "" {
+		parse := parseJSONArray
+		if opts.format == formatINI {
+			parse = parseINI
+		}
+
+		ev, err := parse(data.Bytes())
+		if err != nil {
+			return nil, fmt.Errorf("unable to parse file %q: %s", fp, err.Error())
+		}
+
+		for _, k := range sortedKeys(ev) {
+			opts.record(k, fp, 0, "")
+		}
+
+		return ev, nil
+	}
+
 	ev := make(map[string]string)
 	sc := bufio.NewScanner(data)
 
-	for sc.Scan() {
+	// conds holds the result of every "@if" enclosing the current line,
+	// which is only used if all of them hold
+	var conds []bool
+
+	// defined tracks the keys set by a line in this file, to catch
+	// duplicates
+	defined := make(map[string]bool)
+
+	for line := 1; sc.Scan(); line++ {
+		if opts.compat == compatDocker {
+			k, v, ok, err := parseDockerLine(sc.Text())
+			if err != nil {
+				return nil, fmt.Errorf("unable to parse file %q: %s", fp, err.Error())
+			}
+
+			if ok {
+				ev[k] = v
+				opts.record(k, fp, line, "")
+			}
+
+			continue
+		}
+
+		if opts.compat == compatPython {
+			k, v, ok, err := parsePythonLine(sc.Text(), ev)
+			if err != nil {
+				return nil, fmt.Errorf("unable to parse file %q: %s", fp, err.Error())
+			}
+
+			if ok {
+				ev[k] = v
+				opts.record(k, fp, line, "")
+			}
+
+			continue
+		}
+
+		if trimmed := strings.TrimSpace(sc.Text()); trimmed == "@endif" || startswith(trimmed, "@if ") {
+			conds, err = conditional(trimmed, conds, ev)
+			if err != nil {
+				return nil, fmt.Errorf("unable to parse file %q: %s", fp, err.Error())
+			}
+
+			continue
+		}
+
+		if !allTrue(conds) {
+			continue
+		}
+
+		if target, ok := includeTarget(sc.Text()); ok {
+			included, err := loadIncluded(fp, target, opts)
+			if err != nil {
+				return nil, fmt.Errorf("unable to parse file %q: %s", fp, err.Error())
+			}
+
+			for k, v := range included {
+				ev[k] = v
+			}
+
+			continue
+		}
+
 		k, v := parseLine(sc.Text())
 		if k == "" || v == "" {
 			continue
 		}
 
+		if !validName(k) {
+			if opts.strictNames {
+				return nil, fmt.Errorf("unable to parse file %q: invalid variable name %q", fp, k)
+			}
+
+			warn(warning{
+				Code:    warnInvalidName,
+				File:    fp,
+				Line:    line,
+				Key:     k,
+				Message: fmt.Sprintf("variable name %q in file %q is not a valid shell variable name", k, fp),
+			})
+		}
+
+		v, err = trimAfterQuote(v)
+		if err != nil {
+			return nil, fmt.Errorf("unable to parse file %q: %s: %s", fp, k, err.Error())
+		}
+
+		// single-quoted and backtick-quoted values are literal, anything
+		// else is expanded, decoding escape sequences if double-quoted
+		v, quote := unquote(v)
+		literal := quote == '\'' || quote == '`'
+
+		// template is the value as written, kept for values built from
+		// references
+		template := v
+
+		if !literal && opts.allowSubst {
+			v, err = substituteCommands(v, ev, quote == '"')
+			if err != nil {
+				return nil, fmt.Errorf("unable to parse file %q: %s: %s", fp, k, err.Error())
+			}
+		}
+
+		if !literal {
+			unsetRefs = nil
+			v = expandValue(v, ev, quote == '"')
+
+			if opts.errorOnUnset && len(unsetRefs) > 0 {
+				return nil, fmt.Errorf("unable to parse file %q: %s references %s, which isn't set", fp, k, unsetRefs[0])
+			}
+		}
+
+		// like other references, credentials are only added to values
+		// that are expanded
+		if opts.netrc != nil && !literal {
+			v = withNetrcCredentials(v, opts.netrc)
+		}
+
+		if opts.fileValues && startswith(v, fileValuePrefix) {
+			v, err = readFileValue(v[len(fileValuePrefix):])
+			if err != nil {
+				return nil, fmt.Errorf("unable to read value for %q: %s", k, err.Error())
+			}
+		}
+
+		if defined[k] {
+			if opts.errorOnDuplicate {
+				return nil, fmt.Errorf("unable to parse file %q: duplicate key %s", fp, k)
+			}
+
+			warn(warning{
+				Code:    warnDuplicateKey,
+				File:    fp,
+				Line:    line,
+				Key:     k,
+				Message: fmt.Sprintf("duplicate key %s in file %q, using last value", k, fp),
+			})
+		}
+
+		if literal || !strings.Contains(template, "$") || template == v {
+			template = ""
+		}
+
+		defined[k] = true
 		ev[k] = v
+		opts.record(k, fp, line, template)
+	}
+
+	if len(conds) > 0 {
+		return nil, fmt.Errorf("unable to parse file %q: missing @endif for %d @if block(s)", fp, len(conds))
 	}
 
 	return ev, nil
 }
 
+// conditional applies an "@if KEY == value", "@if KEY != value" or "@endif"
+// directive to the stack of enclosing conditions. Keys are looked up first
+// in the variables parsed so far, then in the base environment.
+func conditional(directive string, conds []bool, ev map[string]string) ([]bool, error) {
+	if directive == "@endif" {
+		if len(conds) == 0 {
+			return nil, fmt.Errorf("@endif without a matching @if")
+		}
+
+		return conds[:len(conds)-1], nil
+	}
+
+	expr := strings.TrimSpace(directive[len("@if"):])
+
+	pos, equal := strings.Index(expr, "=="), true
+	if neq := strings.Index(expr, "!="); neq >= 0 && (pos < 0 || neq < pos) {
+		pos, equal = neq, false
+	}
+
+	if pos <= 0 {
+		return nil, fmt.Errorf("invalid condition %q, expected \"KEY == value\" or \"KEY != value\"", expr)
+	}
+
+	key := strings.ToUpper(strings.TrimSpace(expr[:pos]))
+	want := strings.TrimSpace(expr[pos+2:])
+
+	value, found := ev[key]
+	if !found {
+		value, _ = lookupBase(key)
+	}
+
+	return append(conds, (value == want) == equal), nil
+}
+
+func allTrue(conds []bool) bool {
+	for _, v := range conds {
+		if !v {
+			return false
+		}
+	}
+
+	return true
+}
+
+// includeTarget returns the path of the file pulled in by an "include" or
+// "source" directive, if the line is one.
+func includeTarget(line string) (string, bool) {
+	trimmed := strings.TrimSpace(line)
+
+	for _, directive := range [...]string{"include", "source"} {
+		if startswith(trimmed, directive+" ") || startswith(trimmed, directive+"\t") {
+			target, _ := unquote(strings.TrimSpace(trimmed[len(directive):]))
+			return target, target != ""
+		}
+	}
+
+	return "", false
+}
+
+// loadIncluded reads the env file pulled in by an "include" directive in
+// fp. Relative paths are resolved from the directory of fp, or from the
+// current one if fp isn't a regular file, and a file including itself,
+// directly or not, is an error.
+func loadIncluded(fp, target string, opts parseOptions) (map[string]string, error) {
+	target, err := expand(target)
+	if err != nil {
+		return nil, fmt.Errorf("unable to expand %q in path: %s", "~", err.Error())
+	}
+
+	if !filepath.IsAbs(target) && fp != stdinFile && !startswith(fp, gitPrefix) {
+		target = filepath.Join(filepath.Dir(fp), target)
+	}
+
+	current, _ := filepath.Abs(fp)
+	target, _ = filepath.Abs(target)
+
+	chain := append(append([]string{}, opts.including...), current)
+	if contains(chain, target) {
+		return nil, fmt.Errorf("include cycle: %s -> %s", strings.Join(chain, " -> "), target)
+	}
+
+	opts.including = chain
+	return loadVirtualEnv(target, opts)
+}
+
+// readFileValue returns the contents of the file at fp, with leading and
+// trailing whitespace removed, to be used as a variable's value.
+func readFileValue(fp string) (string, error) {
+	fp, err := expand(fp)
+	if err != nil {
+		return "", fmt.Errorf("unable to expand %q in path: %s", "~", err.Error())
+	}
+
+	data, err := loadFile(fp)
+	if err != nil {
+		return "", err
+	}
+
+	return strings.TrimSpace(data.String()), nil
+}
+
 func parseLine(line string) (string, string) {
 	if startswith(strings.TrimSpace(line), "#") {
 		return "", ""
 	}
 
-	items := strings.Split(line, "=")
-	if len(items) < 2 {
+	line = stripExport(line)
+
+	pos := keySeparator(line)
+	if pos < 0 {
 		return "", ""
 	}
 
-	return strings.ToUpper(items[0]), strings.Join(items[1:], "=")
+	key := strings.ReplaceAll(line[:pos], `\=`, "=")
+	return strings.ToUpper(key), line[pos+1:]
 }
 
-func envOrDefault(key, defval string) string {
-	if v, found := os.LookupEnv(key); found {
-		if s := strings.TrimSpace(v); s != "" {
-			return s
+// stripExport removes the "export" keyword that env files meant to be
+// sourced by a shell put in front of each variable, along with any
+// whitespace around it.
+func stripExport(line string) string {
+	trimmed := strings.TrimLeft(line, " \t")
+
+	if !startswith(trimmed, "export ") && !startswith(trimmed, "export\t") {
+		return line
+	}
+
+	return strings.TrimLeft(trimmed[len("export"):], " \t")
+}
+
+// keySeparator returns the position of the first "=" in the line that
+// isn't escaped with a backslash, or -1 if there's none. Escaping allows
+// keys to contain an equal sign, like "A\=B=value".
+func keySeparator(line string) int {
+	for pos := 0; pos < len(line); pos++ {
+		switch line[pos] {
+		case '\\':
+			if pos+1 < len(line) && line[pos+1] == '=' {
+				pos++
+			}
+
+		case '=':
+			return pos
 		}
 	}
 
-	return defval
+	return -1
 }
 
-func errexit(format string, args ...interface{}) {
-	fmt.Fprintf(os.Stderr, "[dotenv] "+format+"\n", args...)
-	os.Exit(1)
+// parseDockerLine parses a line the way "docker run --env-file" does: keys
+// keep their case, values are taken verbatim (quotes included), empty values
+// are allowed and a bare KEY takes its value from the base environment,
+// being skipped if it isn't set there either.
+func parseDockerLine(line string) (string, string, bool, error) {
+	trimmed := strings.TrimLeft(line, " \t")
+	if trimmed == "" || startswith(trimmed, "#") {
+		return "", "", false, nil
+	}
+
+	items := strings.SplitN(trimmed, "=", 2)
+	key := items[0]
+
+	if key == "" {
+		return "", "", false, fmt.Errorf("no variable name on line %q", line)
+	}
+
+	if strings.ContainsAny(key, " \t") {
+		return "", "", false, fmt.Errorf("variable %q contains whitespaces", key)
+	}
+
+	if len(items) == 2 {
+		return key, items[1], true, nil
+	}
+
+	value, found := lookupBase(key)
+	return key, value, found, nil
+}
+
+// parsePythonLine parses a line the way python-dotenv does: keys keep their
+// case and may have whitespace around the "=", "KEY=" sets an empty value
+// and a bare KEY is skipped. Values are unquoted and expanded following
+// pythonValue and expandPython, with ev holding the variables parsed so far.
+func parsePythonLine(line string, ev map[string]string) (string, string, bool, error) {
+	trimmed := strings.TrimSpace(stripExport(line))
+	if trimmed == "" || startswith(trimmed, "#") {
+		return "", "", false, nil
+	}
+
+	idx := strings.Index(trimmed, "=")
+	if idx < 0 {
+		return "", "", false, nil
+	}
+
+	key := strings.TrimSpace(trimmed[:idx])
+	if key == "" || strings.ContainsAny(key, " \t#") {
+		return "", "", false, fmt.Errorf("invalid variable name %q", key)
+	}
+
+	value, err := pythonValue(strings.TrimLeft(trimmed[idx+1:], " \t"))
+	if err != nil {
+		return "", "", false, fmt.Errorf("%s: %s", key, err.Error())
+	}
+
+	return key, expandPython(value, ev), true, nil
 }
 
-func envFilePresentInHome(filename string) (string, bool) {
-	filename = filepath.Join(dotenvLocations, filename+".env")
-	filename, _ = expand(filename)
+// pythonValue reads a value following python-dotenv's quoting rules.
+// Single-quoted values only decode \\ and \', while double-quoted values
+// also decode \" and the escapes of Python strings, such as \n and \t.
+// Quoted values can be followed by a comment, and unquoted values end at
+// a "#" preceded by whitespace.
+func pythonValue(raw string) (string, error) {
+	if !startswith(raw, "'") && !startswith(raw, `"`) {
+		for pos := 1; pos < len(raw); pos++ {
+			if raw[pos] == '#' && (raw[pos-1] == ' ' || raw[pos-1] == '\t') {
+				raw = raw[:pos]
+				break
+			}
+		}
 
-	info, err := os.Stat(filename)
-	if os.IsNotExist(err) {
-		return "", false
+		return strings.TrimRight(raw, " \t"), nil
+	}
+
+	quote := raw[0]
+
+	end := -1
+	for pos := 1; pos < len(raw); pos++ {
+		if raw[pos] == '\\' {
+			pos++
+			continue
+		}
+
+		if raw[pos] == quote {
+			end = pos
+			break
+		}
+	}
+
+	if end < 0 {
+		return "", fmt.Errorf("missing closing quote, values spanning several lines are not supported")
+	}
+
+	if rest := strings.TrimLeft(raw[end+1:], " \t"); rest != "" && !startswith(rest, "#") {
+		return "", fmt.Errorf("unexpected %q after the closing quote", rest)
+	}
+
+	r := strings.NewReplacer(`\\`, `\`, `\'`, "'")
+	if quote == '"' {
+		r = strings.NewReplacer(
+			`\\`, `\`, `\'`, "'", `\"`, `"`, `\a`, "\a", `\b`, "\b",
+			`\f`, "\f", `\n`, "\n", `\r`, "\r", `\t`, "\t", `\v`, "\v",
+		)
+	}
+
+	return r.Replace(raw[1:end]), nil
+}
+
+// expandPython replaces the "${VAR}" and "${VAR:-default}" references in
+// value the way python-dotenv does: "$VAR" is kept as is, defaults are used
+// only when the variable is unset and aren't expanded themselves, and
+// unset variables expand to an empty string. Like in "dotenv run",
+// variables are looked up first in vars, then in the base environment.
+func expandPython(value string, vars map[string]string) string {
+	var sb strings.Builder
+
+	for {
+		start := strings.Index(value, "${")
+		if start < 0 {
+			break
+		}
+
+		end := strings.IndexByte(value[start:], '}')
+		if end < 0 {
+			break
+		}
+
+		end += start
+		name, def := value[start+2:end], ""
+		if idx := strings.Index(name, ":-"); idx >= 0 {
+			name, def = name[:idx], name[idx+2:]
+		}
+
+		// other operators aren't references for python-dotenv, so they're
+		// kept as written, although they may contain one
+		if strings.Contains(name, ":") {
+			sb.WriteString(value[:start+2])
+			value = value[start+2:]
+			continue
+		}
+
+		v, found := vars[name]
+		if !found {
+			v, found = lookupBase(name)
+		}
+
+		if !found {
+			v = def
+		}
+
+		sb.WriteString(value[:start] + v)
+		value = value[end+1:]
 	}
 
-	return filename, !info.IsDir()
+	sb.WriteString(value)
+	return sb.String()
+}
+
+// missingFromSchema returns the keys defined in the schema file, usually an
+// ".env.example", that aren't set in the given list of KEY=VALUE variables.
+func missingFromSchema(schema string, environ []string) ([]string, error) {
+	fp, err := expand(schema)
+	if err != nil {
+		return nil, fmt.Errorf("unable to expand %q in path: %s", "~", err.Error())
+	}
+
+	data, err := loadFile(fp)
+	if err != nil {
+		return nil, err
+	}
+
+	present := make(map[string]bool, len(environ))
+	for _, v := range environ {
+		k, _ := splitEnvVar(v)
+		present[k] = true
+	}
+
+	var missing []string
+	sc := bufio.NewScanner(bytes.NewReader(bytes.TrimPrefix(data.Bytes(), utf8BOM)))
+
+	for sc.Scan() {
+		k, _ := parseLine(sc.Text())
+		if k == "" || present[k] {
+			continue
+		}
+
+		missing = append(missing, k)
+		present[k] = true
+	}
+
+	return missing, nil
+}
+
+// missingRequired returns the keys that aren't set in environ, in the
+// order they were given. With nonempty, keys set to an empty value are
+// considered missing too.
+func missingRequired(environ []string, keys []string, nonempty bool) []string {
+	env := envMap(environ)
+
+	var missing []string
+	for _, k := range keys {
+		if v, found := env[k]; !found || (nonempty && v == "") {
+			missing = append(missing, k)
+		}
+	}
+
+	return missing
+}
+
+func envOrDefault(key, defval string) string {
+	if v, found := os.LookupEnv(key); found {
+		if s := strings.TrimSpace(v); s != "" {
+			return s
+		}
+	}
+
+	return defval
+}
+
+// lookupEnvOr is like envOrDefault, but a variable set to an empty value is
+// kept empty rather than replaced by the default.
+func lookupEnvOr(key, defval string) string {
+	if v, found := os.LookupEnv(key); found {
+		return strings.TrimSpace(v)
+	}
+
+	return defval
+}
+
+// warnOutput is where warnings are written.
+var warnOutput io.Writer = os.Stderr
+
+// warningsJSON, set with --warnings-format json, writes warnings as one
+// JSON object per line instead of text, so CI steps can parse them.
+var warningsJSON bool
+
+// warning describes a problem that doesn't stop dotenv. Code, file, line
+// and key are only set when known.
+type warning struct {
+	Code    string `json:"code,omitempty"`
+	File    string `json:"file,omitempty"`
+	Line    int    `json:"line,omitempty"`
+	Key     string `json:"key,omitempty"`
+	Message string `json:"message"`
+}
+
+// The codes of the warnings about env files.
+const (
+	warnDuplicateKey = "duplicate-key"
+	warnInvalidName  = "invalid-name"
+	warnControlVar   = "control-var"
+)
+
+func warn(w warning) {
+	if !warningsJSON {
+		fmt.Fprintf(warnOutput, "[dotenv] warning: %s\n", w.Message)
+		return
+	}
+
+	data, err := json.Marshal(w)
+	if err != nil {
+		fmt.Fprintf(warnOutput, "[dotenv] warning: %s\n", w.Message)
+		return
+	}
+
+	fmt.Fprintf(warnOutput, "%s\n", data)
+}
+
+func warnf(format string, args ...interface{}) {
+	warn(warning{Message: fmt.Sprintf(format, args...)})
+}
+
+// controlKeys are the variables that dotenv interprets, instead of passing
+// them to the command, when an env file sets them.
+var controlKeys = [...]string{aliasKey, strictKey}
+
+// takeControlVars removes the control variables set by the env files from
+// envvars, returning them by key. Each one is warned about, since an app may
+// use the same name for its own variable. When disabled, envvars is left
+// untouched so they're passed to the command.
+func takeControlVars(envvars map[string]string, disabled bool) map[string]string {
+	taken := make(map[string]string)
+	if disabled {
+		return taken
+	}
+
+	for _, k := range controlKeys {
+		v, found := envvars[k]
+		if !found {
+			continue
+		}
+
+		warn(warning{
+			Code:    warnControlVar,
+			Key:     k,
+			Message: fmt.Sprintf("env file sets %s, which is interpreted by dotenv and not passed to the command; use --no-control-vars to pass it", k),
+		})
+		taken[k] = v
+		delete(envvars, k)
+	}
+
+	return taken
+}
+
+func errexit(format string, args ...interface{}) {
+	fmt.Fprintf(os.Stderr, "[dotenv] "+format+"\n", args...)
+	os.Exit(1)
+}
+
+// envFilePresentInHome looks for a named environment file in the dotenv
+// folder. It only needs the user's home directory when the folder starts
+// with "~/", which is the default.
+func envFilePresentInHome(filename, ext string) (string, bool, error) {
+	filename = filepath.Join(dotenvLocations, filename+ext)
+
+	filename, err := expand(filename)
+	if err != nil {
+		return "", false, fmt.Errorf("unable to find the home directory: %s -- set $DOTENV_FOLDER_PATH to a full path to avoid needing it", err.Error())
+	}
+
+	info, err := os.Stat(filename)
+	if err != nil {
+		return "", false, nil
+	}
+
+	return filename, !info.IsDir(), nil
+}
+
+// envFilePresentInConfig looks for the named environment in the "dotenv"
+// folder of the user's configuration directory, which is $XDG_CONFIG_HOME
+// or ~/.config on Linux.
+func envFilePresentInConfig(filename, ext string) (string, bool) {
+	dir, err := os.UserConfigDir()
+	if err != nil {
+		return "", false
+	}
+
+	fp := filepath.Join(dir, "dotenv", filename+ext)
+	return fp, isFile(fp)
+}
+
+// isFile reports whether fp exists and is not a directory.
+func isFile(fp string) bool {
+	info, err := os.Stat(fp)
+	return err == nil && !info.IsDir()
+}
+
+// envFileNextTo looks up command like the shell would, using $PATH if
+// it's not a path already, and returns the ".env" file in the same
+// directory as the executable, if there's one.
+func envFileNextTo(command string) (string, bool) {
+	fp, err := exec.LookPath(command)
+	if err != nil {
+		return "", false
+	}
+
+	if resolved, err := filepath.EvalSymlinks(fp); err == nil {
+		fp = resolved
+	}
+
+	fp = filepath.Join(filepath.Dir(fp), ".env")
+	return fp, isFile(fp)
+}
+
+// validName reports whether key is a valid POSIX variable name: letters,
+// digits and underscores, not starting with a digit. Shells can't read or
+// export variables with other names.
+func validName(key string) bool {
+	if key == "" {
+		return false
+	}
+
+	for pos, r := range key {
+		switch {
+		case r == '_', r >= 'A' && r <= 'Z', r >= 'a' && r <= 'z':
+		case r >= '0' && r <= '9' && pos > 0:
+		default:
+			return false
+		}
+	}
+
+	return true
+}
+
+func isSecret(key string) bool {
+	key = strings.ToUpper(key)
+	for _, m := range secretMarkers {
+		if strings.Contains(key, m) {
+			return true
+		}
+	}
+
+	return false
+}
+
+// maskValue hides the value of variables that look like secrets. If
+// maskReveal is set, the first and last maskReveal characters are kept
+// visible, unless the value is too short to hide anything between them.
+func maskValue(key, value string) string {
+	if !isSecret(key) {
+		return value
+	}
+
+	if r := []rune(value); maskReveal > 0 && len(r) > 2*maskReveal {
+		return string(r[:maskReveal]) + "…" + string(r[len(r)-maskReveal:])
+	}
+
+	return "****"
+}
+
+func splitEnvVar(kv string) (string, string) {
+	if pos := strings.Index(kv, "="); pos >= 0 {
+		return kv[:pos], kv[pos+1:]
+	}
+
+	return kv, ""
+}
+
+// fdFile returns the file for the given descriptor. Stdin, stdout and
+// stderr reuse os.Stdin, os.Stdout and os.Stderr: a second file for them
+// would close them once garbage collected, while the command needs them.
+func fdFile(fd int) *os.File {
+	switch fd {
+	case 0:
+		return os.Stdin
+	case 1:
+		return os.Stdout
+	case 2:
+		return os.Stderr
+	}
+
+	return os.NewFile(uintptr(fd), "fd"+strconv.Itoa(fd))
+}
+
+// writeEnvFd writes environ, one KEY=VALUE per line, to the given file
+// descriptor, which is closed afterwards unless it's stdin, stdout or
+// stderr.
+func writeEnvFd(fd int, environ []string) error {
+	f := fdFile(fd)
+	if fd > 2 {
+		defer f.Close()
+	}
+
+	for _, v := range environ {
+		if _, err := f.WriteString(v + "\n"); err != nil {
+			return err
+		}
+	}
+
+	return nil
+}
+
+// inheritedEnv is the environment given with --inherit-from, used instead
+// of the current one as the base environment. It's nil when there's none.
+var inheritedEnv []string
+
+// baseEnviron returns the environment dotenv starts from, the one given
+// with --inherit-from or the current one, as KEY=VALUE entries.
+func baseEnviron() []string {
+	if inheritedEnv != nil {
+		return append([]string{}, inheritedEnv...)
+	}
+
+	return os.Environ()
+}
+
+// lookupBase is like os.LookupEnv, but looks key up in baseEnviron, so env
+// files reference the variables of the environment the command gets.
+func lookupBase(key string) (string, bool) {
+	if inheritedEnv == nil {
+		return os.LookupEnv(key)
+	}
+
+	// like in the environment, the last entry for a key wins
+	for i := len(inheritedEnv) - 1; i >= 0; i-- {
+		if k, v := splitEnvVar(inheritedEnv[i]); k == key {
+			return v, true
+		}
+	}
+
+	return "", false
+}
+
+// inheritFrom returns the environment described by source, to be used as the
+// base environment instead of the current one. The source is either
+// "pid:<pid>", to copy the environment of a running process, or "file:<path>",
+// to read a snapshot with one KEY=VALUE per line such as the output of "env".
+func inheritFrom(source string) ([]string, error) {
+	switch {
+	case startswith(source, "pid:"):
+		pid := source[len("pid:"):]
+		if _, err := strconv.Atoi(pid); err != nil {
+			return nil, fmt.Errorf("invalid process ID %q", pid)
+		}
+
+		data, err := ioutil.ReadFile(filepath.Join("/proc", pid, "environ"))
+		if err != nil {
+			return nil, fmt.Errorf("unable to read environment of process %s: %s", pid, err.Error())
+		}
+
+		return envEntries(string(data), "\x00"), nil
+
+	case startswith(source, "file:"):
+		fp, err := expand(source[len("file:"):])
+		if err != nil {
+			return nil, fmt.Errorf("unable to expand %q in path: %s", "~", err.Error())
+		}
+
+		data, err := loadFile(fp)
+		if err != nil {
+			return nil, err
+		}
+
+		text := string(bytes.TrimPrefix(data.Bytes(), utf8BOM))
+		return envEntries(strings.ReplaceAll(text, "\r\n", "\n"), "\n"), nil
+	}
+
+	return nil, fmt.Errorf("unknown source %q, expected pid:<pid> or file:<path>", source)
+}
+
+// envEntries splits data by sep, keeping only the KEY=VALUE entries.
+func envEntries(data, sep string) []string {
+	var out []string
+
+	for _, v := range strings.Split(data, sep) {
+		if strings.Index(v, "=") > 0 {
+			out = append(out, v)
+		}
+	}
+
+	return out
+}
+
+func contains(list []string, s string) bool {
+	for _, v := range list {
+		if v == s {
+			return true
+		}
+	}
+
+	return false
+}
+
+// setIfUnset adds the KEY=VALUE defaults to envvars for the keys set
+// neither by envvars nor by inherited, the variables the command gets from
+// the environment. It returns the keys it set.
+func setIfUnset(envvars, inherited map[string]string, defaults []string) ([]string, error) {
+	var set []string
+
+	for _, kv := range defaults {
+		k, v := splitEnvVar(kv)
+		if k == "" || !strings.Contains(kv, "=") {
+			return nil, fmt.Errorf("invalid value %q for --set-if-unset, expected KEY=VALUE", kv)
+		}
+
+		if _, found := envvars[k]; found {
+			continue
+		}
+
+		if _, found := inherited[k]; found {
+			continue
+		}
+
+		envvars[k] = v
+		set = append(set, k)
+	}
+
+	return set, nil
+}
+
+// matchingVars returns the KEY=VALUE variables whose key is one of the
+// given names or matches any of the given filepath.Match patterns.
+func matchingVars(environ, names, patterns []string) []string {
+	var out []string
+
+	for _, v := range environ {
+		k, _ := splitEnvVar(v)
+
+		if contains(names, k) || matchesAny(patterns, k) {
+			out = append(out, v)
+		}
+	}
+
+	return out
+}
+
+// matchesAny reports whether key matches any of the given filepath.Match
+// patterns, which also covers plain variable names.
+func matchesAny(patterns []string, key string) bool {
+	for _, p := range patterns {
+		if ok, _ := filepath.Match(p, key); ok {
+			return true
+		}
+	}
+
+	return false
+}
+
+// strictReport compares the parent environment against the one the command
+// gets. It returns the sorted keys of the parent variables that were removed,
+// and the problems found when checking them against the expected patterns:
+// variables removed without matching any of them, and variables matching
+// one of them that were kept anyway.
+func strictReport(parent map[string]string, environ []string, expected []string) ([]string, []string) {
+	final := envMap(environ)
+
+	var stripped, problems []string
+	for _, k := range sortedKeys(parent) {
+		_, kept := final[k]
+
+		switch {
+		case !kept:
+			stripped = append(stripped, k)
+			if len(expected) > 0 && !matchesAny(expected, k) {
+				problems = append(problems, k+" was removed unexpectedly")
+			}
+
+		case matchesAny(expected, k):
+			problems = append(problems, k+" was expected to be removed but was kept")
+		}
+	}
+
+	return stripped, problems
+}
+
+// cwdMatches reports whether the working directory, with symlinks
+// resolved, or any of its parent directories matches one of the given
+// filepath.Match patterns.
+func cwdMatches(patterns []string) (bool, error) {
+	wd, err := os.Getwd()
+	if err != nil {
+		return false, err
+	}
+
+	dir, err := filepath.EvalSymlinks(wd)
+	if err != nil {
+		return false, err
+	}
+
+	for {
+		for _, p := range patterns {
+			if ok, err := filepath.Match(p, dir); err != nil {
+				return false, fmt.Errorf("invalid pattern %q: %s", p, err.Error())
+			} else if ok {
+				return true, nil
+			}
+		}
+
+		parent := filepath.Dir(dir)
+		if parent == dir {
+			return false, nil
+		}
+
+		dir = parent
+	}
+}
+
+func sortedKeys(m map[string]string) []string {
+	keys := make([]string, 0, len(m))
+	for k := range m {
+		keys = append(keys, k)
+	}
+
+	sort.Strings(keys)
+	return keys
+}
+
+// unsetStatement returns the statement that removes key from the
+// environment in the given shell. The key is written as it is, so it fails
+// on keys that aren't valid variable names.
+func unsetStatement(shell, key string) (string, error) {
+	if !validName(key) {
+		return "", fmt.Errorf("%q is not a valid variable name for shells", key)
+	}
+
+	switch shell {
+	case "bash", "zsh", "sh":
+		return "unset " + key, nil
+
+	case "fish":
+		return "set -e " + key, nil
+	}
+
+	return "", fmt.Errorf("unsupported shell %q, supported shells: bash, zsh, sh, fish", shell)
+}
+
+// inferType guesses the kind of data a value holds: bool, int, float,
+// url, json or string. It's only informational.
+func inferType(value string) string {
+	switch strings.ToLower(value) {
+	case "true", "false":
+		return "bool"
+	}
+
+	if _, err := strconv.ParseInt(value, 10, 64); err == nil {
+		return "int"
+	}
+
+	if _, err := strconv.ParseFloat(value, 64); err == nil {
+		return "float"
+	}
+
+	if u, err := url.Parse(value); err == nil && u.Scheme != "" && u.Host != "" {
+		return "url"
+	}
+
+	if (startswith(value, "{") || startswith(value, "[")) && json.Valid([]byte(value)) {
+		return "json"
+	}
+
+	return "string"
+}
+
+// envMap converts a list of KEY=VALUE variables into a map, with later
+// entries overriding earlier ones.
+func envMap(environ []string) map[string]string {
+	out := make(map[string]string, len(environ))
+	for _, v := range environ {
+		k, val := splitEnvVar(v)
+		out[k] = val
+	}
+
+	return out
+}
+
+// summary describes the resolved environment in a single line, like
+// "# 12 variables, 3 from 1 env file, strict mode off, 2 overridden".
+func summary(environ []string, fromFiles, parent map[string]string, files int, strict bool) string {
+	overridden := 0
+	for k, v := range fromFiles {
+		if pv, found := parent[k]; found && pv != v && !strict {
+			overridden++
+		}
+	}
+
+	mode := "off"
+	if strict {
+		mode = "on"
+	}
+
+	noun := "env files"
+	if files == 1 {
+		noun = "env file"
+	}
+
+	return fmt.Sprintf("# %d variables, %d from %d %s, strict mode %s, %d overridden", len(dedupEnv(environ)), len(fromFiles), files, noun, mode, overridden)
+}
+
+// keysWithEquals returns the sorted keys containing "=", which can't be
+// part of an environment: "A=B=value" is read back as A set to "B=value".
+func keysWithEquals(vars map[string]string) []string {
+	var out []string
+
+	for _, k := range sortedKeys(vars) {
+		if strings.Contains(k, "=") {
+			out = append(out, k)
+		}
+	}
+
+	return out
+}
+
+// dedupEnv collapses a list of KEY=VALUE variables so each key appears
+// once, keeping the last value like the command would see it. The result
+// is sorted by key.
+func dedupEnv(environ []string) []string {
+	values := envMap(environ)
+	keys := sortedKeys(values)
+
+	out := make([]string, 0, len(keys))
+	for _, k := range keys {
+		out = append(out, k+"="+values[k])
+	}
+
+	return out
+}
+
+// exitCode returns the exit code to use for a command that failed. If the
+// command was killed by a signal, it's 128 plus the signal number, like
+// shells do, instead of the -1 reported by Go.
+func exitCode(e *exec.ExitError) int {
+	if ws, ok := e.Sys().(syscall.WaitStatus); ok && ws.Signaled() {
+		return 128 + int(ws.Signal())
+	}
+
+	return e.ExitCode()
+}
+
+// commandSeparator separates several commands to run one after the other
+// with the same environment.
+const commandSeparator = ":::"
+
+// commandList returns the commands to run for argv. The command separator
+// is only one with chain, since commands like GNU parallel take it as an
+// argument.
+func commandList(argv []string, chain bool) ([][]string, error) {
+	if !chain {
+		return [][]string{argv}, nil
+	}
+
+	return splitCommands(argv)
+}
+
+// splitCommands splits argv at every command separator, returning the
+// program and arguments of each command to run.
+func splitCommands(argv []string) ([][]string, error) {
+	var (
+		out     [][]string
+		current []string
+	)
+
+	for _, v := range append(argv, commandSeparator) {
+		if v != commandSeparator {
+			current = append(current, v)
+			continue
+		}
+
+		if len(current) == 0 {
+			return nil, fmt.Errorf("missing command around %q", commandSeparator)
+		}
+
+		out = append(out, current)
+		current = nil
+	}
+
+	return out, nil
+}
+
+// traceExec prints to w the program, arguments, working directory and
+// environment dotenv is about to execute. Only the variables coming from the
+// env file are listed, and their values are masked if they look like secrets.
+func traceExec(w io.Writer, cmd *exec.Cmd, injected []string, inherited int) {
+	wd, err := os.Getwd()
+	if err != nil {
+		wd = "unknown: " + err.Error()
+	}
+
+	fmt.Fprintf(w, "[dotenv] exec: program: %s\n", cmd.Path)
+	fmt.Fprintf(w, "[dotenv] exec: argv: %q\n", cmd.Args)
+	fmt.Fprintf(w, "[dotenv] exec: dir: %s\n", wd)
+	fmt.Fprintf(w, "[dotenv] exec: env: %d inherited variables, %d from env file\n", inherited, len(injected))
+
+	sorted := append([]string{}, injected...)
+	sort.Strings(sorted)
+
+	for _, v := range sorted {
+		k, val := splitEnvVar(v)
+		fmt.Fprintf(w, "[dotenv] exec: env: %s=%s\n", k, maskValue(k, val))
+	}
 }
@@ -0,0 +1,815 @@
+package main
+
+import (
+	"bytes"
+	"encoding/json"
+	"io/ioutil"
+	"math/rand"
+	"os"
+	"os/exec"
+	"path/filepath"
+	"reflect"
+	"strings"
+	"testing"
+	"testing/quick"
+)
+
+func TestKeySeparator(t *testing.T) {
+	cases := []struct {
+		line string
+		want int
+	}{
+		{line: "KEY=value", want: 3},
+		{line: "=value", want: 0},
+		{line: `A\=B=value`, want: 4},
+		{line: `A\B=value`, want: 3},
+		{line: `A\=B`, want: -1},
+		{line: "no separator", want: -1},
+	}
+
+	for _, tc := range cases {
+		if got := keySeparator(tc.line); got != tc.want {
+			t.Errorf("keySeparator(%q) = %d, want %d", tc.line, got, tc.want)
+		}
+	}
+}
+
+func TestParseLine(t *testing.T) {
+	cases := []struct {
+		line  string
+		key   string
+		value string
+	}{
+		{line: "key=value", key: "KEY", value: "value"},
+		{line: "KEY=a=b", key: "KEY", value: "a=b"},
+		{line: "export KEY=value", key: "KEY", value: "value"},
+		{line: "  export\tKEY='quoted'", key: "KEY", value: "'quoted'"},
+		{line: "exported=value", key: "EXPORTED", value: "value"},
+		{line: `A\=B=value`, key: "A=B", value: "value"},
+		{line: "KEY=", key: "KEY", value: ""},
+		{line: "  # KEY=value"},
+		{line: "no separator"},
+	}
+
+	for _, tc := range cases {
+		key, value := parseLine(tc.line)
+		if key != tc.key || value != tc.value {
+			t.Errorf("parseLine(%q) = %q, %q, want %q, %q", tc.line, key, value, tc.key, tc.value)
+		}
+	}
+}
+
+func TestTraceExec(t *testing.T) {
+	wd, err := os.Getwd()
+	if err != nil {
+		t.Fatal(err)
+	}
+
+	cmd := exec.Command("/usr/local/bin/app", "--name", "two words")
+
+	var out bytes.Buffer
+	traceExec(&out, cmd, []string{"HOST=localhost", "API_TOKEN=abcdef"}, 3)
+
+	want := "[dotenv] exec: program: /usr/local/bin/app\n" +
+		"[dotenv] exec: argv: [\"/usr/local/bin/app\" \"--name\" \"two words\"]\n" +
+		"[dotenv] exec: dir: " + wd + "\n" +
+		"[dotenv] exec: env: 3 inherited variables, 2 from env file\n" +
+		"[dotenv] exec: env: API_TOKEN=****\n" +
+		"[dotenv] exec: env: HOST=localhost\n"
+
+	if got := out.String(); got != want {
+		t.Errorf("traceExec() printed %q, want %q", got, want)
+	}
+
+	if strings.Contains(out.String(), "abcdef") {
+		t.Errorf("traceExec() printed the value of a secret: %q", out.String())
+	}
+}
+
+func TestSplitCommands(t *testing.T) {
+	cases := []struct {
+		argv    []string
+		want    [][]string
+		wantErr bool
+	}{
+		{argv: []string{"ls", "-l"}, want: [][]string{{"ls", "-l"}}},
+		{argv: []string{"make", "build", ":::", "make", "test"}, want: [][]string{{"make", "build"}, {"make", "test"}}},
+		{argv: []string{"echo", ":::x"}, want: [][]string{{"echo", ":::x"}}},
+		{argv: []string{":::", "ls"}, wantErr: true},
+		{argv: []string{"ls", ":::"}, wantErr: true},
+		{argv: []string{"ls", ":::", ":::", "pwd"}, wantErr: true},
+	}
+
+	for _, tc := range cases {
+		got, err := splitCommands(tc.argv)
+		if (err != nil) != tc.wantErr {
+			t.Errorf("splitCommands(%q) error = %v, want error: %v", tc.argv, err, tc.wantErr)
+			continue
+		}
+
+		if !tc.wantErr && !reflect.DeepEqual(got, tc.want) {
+			t.Errorf("splitCommands(%q) = %q, want %q", tc.argv, got, tc.want)
+		}
+	}
+}
+
+func TestCommandList(t *testing.T) {
+	argv := []string{"parallel", "echo", ":::", "a", "b"}
+
+	if got, err := commandList(argv, false); err != nil || !reflect.DeepEqual(got, [][]string{argv}) {
+		t.Errorf("commandList(%q, false) = %q, %v, want %q", argv, got, err, [][]string{argv})
+	}
+
+	want := [][]string{{"parallel", "echo"}, {"a", "b"}}
+	if got, err := commandList(argv, true); err != nil || !reflect.DeepEqual(got, want) {
+		t.Errorf("commandList(%q, true) = %q, %v, want %q", argv, got, err, want)
+	}
+
+	if _, err := commandList([]string{"ls", ":::"}, false); err != nil {
+		t.Errorf("commandList() without --chain error = %v, want none", err)
+	}
+}
+
+func TestDuplicateKeys(t *testing.T) {
+	dir, err := ioutil.TempDir("", "dotenv")
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer os.RemoveAll(dir)
+
+	base := filepath.Join(dir, "base.env")
+	local := filepath.Join(dir, "local.env")
+
+	if err := ioutil.WriteFile(base, []byte("PORT=80\nHOST=localhost\n"), 0644); err != nil {
+		t.Fatal(err)
+	}
+
+	if err := ioutil.WriteFile(local, []byte("PORT=8080\nDEBUG=true\n"), 0644); err != nil {
+		t.Fatal(err)
+	}
+
+	vars, sources, err := loadEnvFiles([]string{base, local}, parseOptions{})
+	if err != nil {
+		t.Fatalf("loadEnvFiles() error = %v", err)
+	}
+
+	if vars["PORT"] != "8080" {
+		t.Errorf("PORT = %q, want %q", vars["PORT"], "8080")
+	}
+
+	want := []string{"PORT: " + base + ", " + local}
+	if got := duplicateKeys(sources); !reflect.DeepEqual(got, want) {
+		t.Errorf("duplicateKeys() = %q, want %q", got, want)
+	}
+}
+
+func TestParseDockerLine(t *testing.T) {
+	inheritedEnv = []string{"FROM_ENV=inherited"}
+	defer func() { inheritedEnv = nil }()
+
+	cases := []struct {
+		line    string
+		key     string
+		value   string
+		ok      bool
+		wantErr bool
+	}{
+		{line: "FOO=bar", key: "FOO", value: "bar", ok: true},
+		{line: "lower=\"quoted\" # kept", key: "lower", value: "\"quoted\" # kept", ok: true},
+		{line: "EMPTY=", key: "EMPTY", value: "", ok: true},
+		{line: "FOO=a=b", key: "FOO", value: "a=b", ok: true},
+		{line: "FROM_ENV", key: "FROM_ENV", value: "inherited", ok: true},
+		{line: "  FROM_ENV", key: "FROM_ENV", value: "inherited", ok: true},
+		{line: "DOTENV_TEST_UNSET", key: "DOTENV_TEST_UNSET"},
+		{line: "# FOO=bar"},
+		{line: "   # FOO=bar"},
+		{line: "FOO=bar#baz", key: "FOO", value: "bar#baz", ok: true},
+		{line: ""},
+		{line: "FOO BAR=baz", wantErr: true},
+		{line: "FOO =bar", wantErr: true},
+		{line: "FOO\t", wantErr: true},
+		{line: "=bar", wantErr: true},
+	}
+
+	for _, tc := range cases {
+		key, value, ok, err := parseDockerLine(tc.line)
+		if (err != nil) != tc.wantErr {
+			t.Errorf("parseDockerLine(%q) error = %v, want error: %v", tc.line, err, tc.wantErr)
+			continue
+		}
+
+		if key != tc.key || value != tc.value || ok != tc.ok {
+			t.Errorf("parseDockerLine(%q) = %q, %q, %v, want %q, %q, %v", tc.line, key, value, ok, tc.key, tc.value, tc.ok)
+		}
+	}
+}
+
+func TestParsePythonLine(t *testing.T) {
+	cases := []struct {
+		line    string
+		key     string
+		value   string
+		ok      bool
+		wantErr bool
+	}{
+		{line: "FOO=bar", key: "FOO", value: "bar", ok: true},
+		{line: "export lower_case = bar  ", key: "lower_case", value: "bar", ok: true},
+		{line: "EMPTY=", key: "EMPTY", value: "", ok: true},
+		{line: "BARE"},
+		{line: "  # FOO=bar"},
+		{line: "FOO=bar # comment", key: "FOO", value: "bar", ok: true},
+		{line: "FOO=bar#baz", key: "FOO", value: "bar#baz", ok: true},
+		{line: `FOO='it\'s # not a comment' # comment`, key: "FOO", value: "it's # not a comment", ok: true},
+		{line: `FOO='C:\path\n'`, key: "FOO", value: `C:\path\n`, ok: true},
+		{line: `FOO="a\n\t\"b\"\\"`, key: "FOO", value: "a\n\t\"b\"\\", ok: true},
+		{line: "URL=${HOST}:8080", key: "URL", value: "localhost:8080", ok: true},
+		{line: "URL='${HOST}'", key: "URL", value: "localhost", ok: true},
+		{line: "URL=$HOST", key: "URL", value: "$HOST", ok: true},
+		{line: `URL="${DOTENV_TEST_UNSET:-default}/x"`, key: "URL", value: "default/x", ok: true},
+		{line: "URL=${EMPTY:-default}", key: "URL", value: "", ok: true},
+		{line: "URL=${host}", key: "URL", value: "", ok: true},
+		{line: "URL=${HOST:=x}", key: "URL", value: "${HOST:=x}", ok: true},
+		{line: `FOO="unterminated`, wantErr: true},
+		{line: "FOO='a' b", wantErr: true},
+		{line: "=bar", wantErr: true},
+	}
+
+	for _, tc := range cases {
+		ev := map[string]string{"HOST": "localhost", "EMPTY": ""}
+
+		key, value, ok, err := parsePythonLine(tc.line, ev)
+		if (err != nil) != tc.wantErr {
+			t.Errorf("parsePythonLine(%q) error = %v, want error: %v", tc.line, err, tc.wantErr)
+			continue
+		}
+
+		if key != tc.key || value != tc.value || ok != tc.ok {
+			t.Errorf("parsePythonLine(%q) = %q, %q, %v, want %q, %q, %v", tc.line, key, value, ok, tc.key, tc.value, tc.ok)
+		}
+	}
+}
+
+func TestStrictReport(t *testing.T) {
+	parent := map[string]string{"PATH": "/bin", "HOME": "/root", "AWS_KEY": "x", "AWS_REGION": "eu", "EDITOR": "vi"}
+	environ := []string{"PATH=/bin", "HOME=/root", "AWS_REGION=eu", "FROM_ENV_FILE=1"}
+
+	cases := []struct {
+		expected []string
+		problems []string
+	}{
+		{expected: nil, problems: nil},
+		{expected: []string{"AWS_KEY", "EDITOR"}, problems: nil},
+		{expected: []string{"AWS_*"}, problems: []string{"AWS_REGION was expected to be removed but was kept", "EDITOR was removed unexpectedly"}},
+		{expected: []string{"EDITOR"}, problems: []string{"AWS_KEY was removed unexpectedly"}},
+	}
+
+	for _, tc := range cases {
+		stripped, problems := strictReport(parent, environ, tc.expected)
+
+		if want := []string{"AWS_KEY", "EDITOR"}; !reflect.DeepEqual(stripped, want) {
+			t.Errorf("strictReport(%q) stripped = %q, want %q", tc.expected, stripped, want)
+		}
+
+		if !reflect.DeepEqual(problems, tc.problems) {
+			t.Errorf("strictReport(%q) problems = %q, want %q", tc.expected, problems, tc.problems)
+		}
+	}
+}
+
+func TestUnsetStatement(t *testing.T) {
+	cases := []struct {
+		shell   string
+		key     string
+		want    string
+		wantErr bool
+	}{
+		{shell: "bash", key: "API_KEY", want: "unset API_KEY"},
+		{shell: "fish", key: "API_KEY", want: "set -e API_KEY"},
+		{shell: "powershell", key: "API_KEY", wantErr: true},
+		{shell: "bash", key: "A; rm -rf ~", wantErr: true},
+		{shell: "sh", key: "A=B", wantErr: true},
+		{shell: "fish", key: "$(id)", wantErr: true},
+	}
+
+	for _, tc := range cases {
+		got, err := unsetStatement(tc.shell, tc.key)
+		if (err != nil) != tc.wantErr {
+			t.Errorf("unsetStatement(%q, %q) error = %v, want error: %v", tc.shell, tc.key, err, tc.wantErr)
+			continue
+		}
+
+		if got != tc.want {
+			t.Errorf("unsetStatement(%q, %q) = %q, want %q", tc.shell, tc.key, got, tc.want)
+		}
+	}
+}
+
+func TestSetIfUnset(t *testing.T) {
+	environ := []string{"TZ=UTC", "LANG=C", "HOME=/root"}
+	defaults := []string{"TZ=Europe/Paris", "LANG=en_US.UTF-8", "HOME=/tmp", "PORT=8080"}
+
+	cases := []struct {
+		name      string
+		inherited map[string]string
+		want      map[string]string
+	}{
+		{
+			name:      "outside strict mode",
+			inherited: envMap(environ),
+			want:      map[string]string{"PORT": "8080"},
+		},
+		{
+			name:      "strict mode keeping TZ and locale variables",
+			inherited: envMap(matchingVars(environ, []string{"TZ"}, keepCategories["locale"])),
+			want:      map[string]string{"HOME": "/tmp", "PORT": "8080"},
+		},
+		{
+			name:      "strict mode keeping nothing",
+			inherited: envMap(matchingVars(environ, nil, nil)),
+			want:      map[string]string{"TZ": "Europe/Paris", "LANG": "en_US.UTF-8", "HOME": "/tmp", "PORT": "8080"},
+		},
+	}
+
+	for _, tc := range cases {
+		envvars := map[string]string{}
+		if _, err := setIfUnset(envvars, tc.inherited, defaults); err != nil {
+			t.Fatalf("%s: setIfUnset() error = %v", tc.name, err)
+		}
+
+		if !reflect.DeepEqual(envvars, tc.want) {
+			t.Errorf("%s: setIfUnset() = %v, want %v", tc.name, envvars, tc.want)
+		}
+	}
+
+	if _, err := setIfUnset(map[string]string{}, nil, []string{"NOVALUE"}); err == nil {
+		t.Errorf("setIfUnset() with %q succeeded, want an error", "NOVALUE")
+	}
+}
+
+func TestLoadVirtualEnvTrailingComments(t *testing.T) {
+	dir, err := ioutil.TempDir("", "dotenv")
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer os.RemoveAll(dir)
+
+	good := filepath.Join(dir, "good.env")
+	if err := ioutil.WriteFile(good, []byte("A=\"x # y\" # comment\nB='$HOME'  # comment\nC=plain # kept\n"), 0644); err != nil {
+		t.Fatal(err)
+	}
+
+	want := map[string]string{"A": "x # y", "B": "$HOME", "C": "plain # kept"}
+	if got, err := loadVirtualEnv(good, parseOptions{}); err != nil || !reflect.DeepEqual(got, want) {
+		t.Errorf("loadVirtualEnv() = %v, %v, want %v", got, err, want)
+	}
+
+	bad := filepath.Join(dir, "bad.env")
+	if err := ioutil.WriteFile(bad, []byte("A=\"x\" y # c\n"), 0644); err != nil {
+		t.Fatal(err)
+	}
+
+	if _, err := loadVirtualEnv(bad, parseOptions{}); err == nil {
+		t.Errorf("loadVirtualEnv() with text after the closing quote succeeded, want an error")
+	}
+}
+
+func TestConditional(t *testing.T) {
+	inheritedEnv = []string{"STAGE=prod"}
+	defer func() { inheritedEnv = nil }()
+
+	cases := []struct {
+		directive string
+		conds     []bool
+		want      []bool
+		wantErr   bool
+	}{
+		{directive: "@if STAGE == prod", want: []bool{true}},
+		{directive: "@if stage==prod", want: []bool{true}},
+		{directive: "@if STAGE != prod", want: []bool{false}},
+		{directive: "@if STAGE == dev", conds: []bool{true}, want: []bool{true, false}},
+		{directive: "@if REGION == eu", want: []bool{true}},
+		{directive: "@if MISSING == ", want: []bool{true}},
+		{directive: "@endif", conds: []bool{true, false}, want: []bool{true}},
+		{directive: "@endif", wantErr: true},
+		{directive: "@if STAGE", wantErr: true},
+		{directive: "@if == prod", wantErr: true},
+	}
+
+	for _, tc := range cases {
+		got, err := conditional(tc.directive, tc.conds, map[string]string{"REGION": "eu"})
+		if (err != nil) != tc.wantErr {
+			t.Errorf("conditional(%q, %v) error = %v, want error: %v", tc.directive, tc.conds, err, tc.wantErr)
+			continue
+		}
+
+		if !tc.wantErr && !reflect.DeepEqual(got, tc.want) {
+			t.Errorf("conditional(%q, %v) = %v, want %v", tc.directive, tc.conds, got, tc.want)
+		}
+	}
+}
+
+func TestLoadVirtualEnvConditionals(t *testing.T) {
+	dir, err := ioutil.TempDir("", "dotenv")
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer os.RemoveAll(dir)
+
+	fp := filepath.Join(dir, ".env")
+	data := `STAGE=prod
+@if STAGE == prod
+URL=https://example.com
+  @if REGION == eu
+REGION_URL=https://eu.example.com
+  @endif
+  @if REGION != eu
+REGION_URL=https://us.example.com
+  @endif
+@endif
+@if STAGE == dev
+URL=http://localhost
+  @if REGION != eu
+DEBUG=true
+  @endif
+@endif
+`
+	if err := ioutil.WriteFile(fp, []byte(data), 0644); err != nil {
+		t.Fatal(err)
+	}
+
+	inheritedEnv = []string{"REGION=us"}
+	defer func() { inheritedEnv = nil }()
+
+	want := map[string]string{"STAGE": "prod", "URL": "https://example.com", "REGION_URL": "https://us.example.com"}
+	if got, err := loadVirtualEnv(fp, parseOptions{}); err != nil || !reflect.DeepEqual(got, want) {
+		t.Errorf("loadVirtualEnv() = %v, %v, want %v", got, err, want)
+	}
+
+	for _, data := range []string{"@if STAGE == prod\nA=1\n", "A=1\n@endif\n", "@if STAGE == prod\n@endif\n@endif\n"} {
+		if err := ioutil.WriteFile(fp, []byte(data), 0644); err != nil {
+			t.Fatal(err)
+		}
+
+		if _, err := loadVirtualEnv(fp, parseOptions{}); err == nil {
+			t.Errorf("loadVirtualEnv() with unbalanced blocks %q succeeded, want an error", data)
+		}
+	}
+}
+
+func TestLoadVirtualEnvInclude(t *testing.T) {
+	dir, err := ioutil.TempDir("", "dotenv")
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer os.RemoveAll(dir)
+
+	if err := os.Mkdir(filepath.Join(dir, "shared"), 0755); err != nil {
+		t.Fatal(err)
+	}
+
+	files := map[string]string{
+		".env":              "NAME=app\ninclude shared/common.env\nURL=http://${HOST}:${PORT}\n",
+		"shared/common.env": "HOST=localhost\nsource \"../ports.env\"\n",
+		"ports.env":         "PORT=8080\nNAME=common\n",
+		"a.env":             "A=1\ninclude b.env\n",
+		"b.env":             "B=2\nsource ./a.env\n",
+	}
+
+	for name, data := range files {
+		if err := ioutil.WriteFile(filepath.Join(dir, name), []byte(data), 0644); err != nil {
+			t.Fatal(err)
+		}
+	}
+
+	want := map[string]string{"NAME": "common", "HOST": "localhost", "PORT": "8080", "URL": "http://localhost:8080"}
+	if got, err := loadVirtualEnv(filepath.Join(dir, ".env"), parseOptions{}); err != nil || !reflect.DeepEqual(got, want) {
+		t.Errorf("loadVirtualEnv() = %v, %v, want %v", got, err, want)
+	}
+
+	_, err = loadVirtualEnv(filepath.Join(dir, "a.env"), parseOptions{})
+	if err == nil || !strings.Contains(err.Error(), "include cycle") {
+		t.Errorf("loadVirtualEnv() with an include cycle = %v, want an include cycle error", err)
+	}
+}
+
+func TestByteOrderMark(t *testing.T) {
+	dir, err := ioutil.TempDir("", "dotenv")
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer os.RemoveAll(dir)
+
+	fp := filepath.Join(dir, ".env")
+	if err := ioutil.WriteFile(fp, []byte("\xef\xbb\xbfFIRST=1\r\nSECOND=2\r\n"), 0644); err != nil {
+		t.Fatal(err)
+	}
+
+	want := map[string]string{"FIRST": "1", "SECOND": "2"}
+	if got, err := loadVirtualEnv(fp, parseOptions{}); err != nil || !reflect.DeepEqual(got, want) {
+		t.Errorf("loadVirtualEnv() = %v, %v, want %v", got, err, want)
+	}
+
+	if missing, err := missingFromSchema(fp, []string{"FIRST=1"}); err != nil || !reflect.DeepEqual(missing, []string{"SECOND"}) {
+		t.Errorf("missingFromSchema() = %q, %v, want %q", missing, err, []string{"SECOND"})
+	}
+
+	if seeded, err := inheritFrom("file:" + fp); err != nil || !reflect.DeepEqual(seeded, []string{"FIRST=1", "SECOND=2"}) {
+		t.Errorf("inheritFrom() = %q, %v, want %q", seeded, err, []string{"FIRST=1", "SECOND=2"})
+	}
+}
+
+func TestLoadVirtualEnvInheritFrom(t *testing.T) {
+	dir, err := ioutil.TempDir("", "dotenv")
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer os.RemoveAll(dir)
+
+	snapshot := filepath.Join(dir, "snapshot")
+	if err := ioutil.WriteFile(snapshot, []byte("STAGE=prod\nREGION=eu\nREGION=us\n"), 0644); err != nil {
+		t.Fatal(err)
+	}
+
+	envfile := filepath.Join(dir, "app.env")
+	data := "URL=https://${STAGE}.example.com/$REGION\nHOME_DIR=${HOME}\n@if STAGE == prod\nDEBUG=false\n@endif\n"
+	if err := ioutil.WriteFile(envfile, []byte(data), 0644); err != nil {
+		t.Fatal(err)
+	}
+
+	seeded, err := inheritFrom("file:" + snapshot)
+	if err != nil {
+		t.Fatalf("inheritFrom() error = %v", err)
+	}
+
+	inheritedEnv = seeded
+	defer func() { inheritedEnv = nil }()
+
+	want := map[string]string{"URL": "https://prod.example.com/us", "HOME_DIR": "", "DEBUG": "false"}
+	if got, err := loadVirtualEnv(envfile, parseOptions{}); err != nil || !reflect.DeepEqual(got, want) {
+		t.Errorf("loadVirtualEnv() = %v, %v, want %v", got, err, want)
+	}
+
+	docker := filepath.Join(dir, "docker.env")
+	if err := ioutil.WriteFile(docker, []byte("STAGE\nHOME\n"), 0644); err != nil {
+		t.Fatal(err)
+	}
+
+	want = map[string]string{"STAGE": "prod"}
+	if got, err := loadVirtualEnv(docker, parseOptions{compat: compatDocker}); err != nil || !reflect.DeepEqual(got, want) {
+		t.Errorf("loadVirtualEnv(compat: docker) = %v, %v, want %v", got, err, want)
+	}
+}
+
+func TestValidName(t *testing.T) {
+	cases := []struct {
+		key  string
+		want bool
+	}{
+		{key: "FOO", want: true},
+		{key: "foo_bar2", want: true},
+		{key: "_LEADING", want: true},
+		{key: "_", want: true},
+		{key: "1FOO", want: false},
+		{key: "MY-VAR", want: false},
+		{key: "my.var", want: false},
+		{key: "A B", want: false},
+		{key: "CAFÉ", want: false},
+		{key: "", want: false},
+	}
+
+	for _, tc := range cases {
+		if got := validName(tc.key); got != tc.want {
+			t.Errorf("validName(%q) = %v, want %v", tc.key, got, tc.want)
+		}
+	}
+}
+
+func TestInferType(t *testing.T) {
+	cases := []struct {
+		value string
+		want  string
+	}{
+		{value: "true", want: "bool"},
+		{value: "FALSE", want: "bool"},
+		{value: "42", want: "int"},
+		{value: "-7", want: "int"},
+		{value: "3.14", want: "float"},
+		{value: "1e3", want: "float"},
+		{value: "https://example.com/path", want: "url"},
+		{value: "{}", want: "json"},
+		{value: `[1, "two"]`, want: "json"},
+		{value: "{not json", want: "string"},
+		{value: "localhost", want: "string"},
+		{value: "", want: "string"},
+	}
+
+	for _, tc := range cases {
+		if got := inferType(tc.value); got != tc.want {
+			t.Errorf("inferType(%q) = %q, want %q", tc.value, got, tc.want)
+		}
+	}
+}
+
+func TestKeysWithEquals(t *testing.T) {
+	vars := map[string]string{"PLAIN": "a=b", "B=C": "x", "A=B": "y", "D": ""}
+
+	want := []string{"A=B", "B=C"}
+	if got := keysWithEquals(vars); !reflect.DeepEqual(got, want) {
+		t.Errorf("keysWithEquals() = %q, want %q", got, want)
+	}
+
+	if got := keysWithEquals(map[string]string{"KEY": "a=b"}); got != nil {
+		t.Errorf("keysWithEquals() = %q, want none", got)
+	}
+}
+
+func TestTakeControlVars(t *testing.T) {
+	var out bytes.Buffer
+	warnOutput = &out
+	defer func() { warnOutput = os.Stderr }()
+
+	envvars := map[string]string{aliasKey: "make test", strictKey: "1", "DOTENV_HOME": "/app"}
+
+	got := takeControlVars(envvars, false)
+	if want := map[string]string{aliasKey: "make test", strictKey: "1"}; !reflect.DeepEqual(got, want) {
+		t.Errorf("takeControlVars() = %v, want %v", got, want)
+	}
+
+	if want := map[string]string{"DOTENV_HOME": "/app"}; !reflect.DeepEqual(envvars, want) {
+		t.Errorf("variables left after takeControlVars() = %v, want %v", envvars, want)
+	}
+
+	for _, k := range []string{aliasKey, strictKey} {
+		if !strings.Contains(out.String(), "warning: env file sets "+k) {
+			t.Errorf("takeControlVars() didn't warn about %s: %q", k, out.String())
+		}
+	}
+
+	out.Reset()
+	envvars = map[string]string{aliasKey: "make test", strictKey: "1"}
+
+	if got := takeControlVars(envvars, true); len(got) != 0 {
+		t.Errorf("takeControlVars() with --no-control-vars = %v, want none", got)
+	}
+
+	if want := map[string]string{aliasKey: "make test", strictKey: "1"}; !reflect.DeepEqual(envvars, want) {
+		t.Errorf("variables passed through with --no-control-vars = %v, want %v", envvars, want)
+	}
+
+	if out.Len() > 0 {
+		t.Errorf("takeControlVars() with --no-control-vars warned: %q", out.String())
+	}
+}
+
+func TestDuplicateKeyWarning(t *testing.T) {
+	dir, err := ioutil.TempDir("", "dotenv")
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer os.RemoveAll(dir)
+
+	fp := filepath.Join(dir, "app.env")
+	if err := ioutil.WriteFile(fp, []byte("PORT=80\nHOST=localhost\nPORT=8080\n"), 0644); err != nil {
+		t.Fatal(err)
+	}
+
+	var out bytes.Buffer
+	warnOutput = &out
+	defer func() { warnOutput = os.Stderr }()
+
+	got, err := loadVirtualEnv(fp, parseOptions{})
+	if err != nil {
+		t.Fatalf("loadVirtualEnv() error = %v", err)
+	}
+
+	if got["PORT"] != "8080" {
+		t.Errorf("PORT = %q, want the last value %q", got["PORT"], "8080")
+	}
+
+	if !strings.Contains(out.String(), "duplicate key PORT") || strings.Count(out.String(), "\n") != 1 {
+		t.Errorf("warnings = %q, want a single duplicate key warning for PORT", out.String())
+	}
+
+	if _, err := loadVirtualEnv(fp, parseOptions{errorOnDuplicate: true}); err == nil || !strings.Contains(err.Error(), "duplicate key PORT") {
+		t.Errorf("loadVirtualEnv(errorOnDuplicate: true) error = %v, want a duplicate key error", err)
+	}
+
+	// setting a key again in a later file isn't a duplicate
+	out.Reset()
+
+	local := filepath.Join(dir, "local.env")
+	if err := ioutil.WriteFile(local, []byte("PORT=9090\n"), 0644); err != nil {
+		t.Fatal(err)
+	}
+
+	if _, _, err := loadEnvFiles([]string{fp, local}, parseOptions{}); err != nil {
+		t.Fatalf("loadEnvFiles() error = %v", err)
+	}
+
+	if strings.Contains(out.String(), local) || strings.Count(out.String(), "\n") != 1 {
+		t.Errorf("warnings = %q, want only the one for %q", out.String(), fp)
+	}
+}
+
+func TestWarningsJSON(t *testing.T) {
+	dir, err := ioutil.TempDir("", "dotenv")
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer os.RemoveAll(dir)
+
+	fp := filepath.Join(dir, "app.env")
+	if err := ioutil.WriteFile(fp, []byte("# settings\nPORT=80\nPORT=8080\n"), 0644); err != nil {
+		t.Fatal(err)
+	}
+
+	var out bytes.Buffer
+	warnOutput, warningsJSON = &out, true
+	defer func() { warnOutput, warningsJSON = os.Stderr, false }()
+
+	if _, err := loadVirtualEnv(fp, parseOptions{}); err != nil {
+		t.Fatalf("loadVirtualEnv() error = %v", err)
+	}
+
+	var got warning
+	if err := json.Unmarshal(out.Bytes(), &got); err != nil {
+		t.Fatalf("warning %q isn't a JSON object: %v", out.String(), err)
+	}
+
+	want := warning{Code: warnDuplicateKey, File: fp, Line: 3, Key: "PORT", Message: got.Message}
+	if got != want || got.Message == "" {
+		t.Errorf("warning = %+v, want %+v", got, want)
+	}
+}
+
+// fuzzAlphabet holds the characters the parsers treat specially, and some
+// multibyte ones, so random lines hit their edge cases more often than
+// random strings would.
+var fuzzAlphabet = []rune("=$:{}()-+#@'\"`\\ \t\r\nKEYxé😀09")
+
+func TestParseNeverPanics(t *testing.T) {
+	dir, err := ioutil.TempDir("", "dotenv")
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer os.RemoveAll(dir)
+
+	warnOutput = ioutil.Discard
+	defer func() { warnOutput = os.Stderr }()
+
+	fp := filepath.Join(dir, "fuzz.env")
+
+	config := &quick.Config{
+		MaxCount: 2000,
+		Values: func(args []reflect.Value, r *rand.Rand) {
+			line := make([]rune, r.Intn(40))
+			for i := range line {
+				line[i] = fuzzAlphabet[r.Intn(len(fuzzAlphabet))]
+			}
+
+			args[0] = reflect.ValueOf(string(line))
+		},
+	}
+
+	// the results don't matter, only that every call returns
+	check := func(s string) bool {
+		vars := map[string]string{"KEY": "value", "EMPTY": ""}
+		expandValue(s, vars, false)
+		expandValue(s, vars, true)
+		expandPython(s, vars)
+		pythonValue(s)
+		trimAfterQuote(s)
+
+		if err := ioutil.WriteFile(fp, []byte("KEY="+s+"\n"+s+"\n"), 0644); err != nil {
+			t.Fatal(err)
+		}
+
+		for _, compat := range []string{"", compatDocker, compatPython} {
+			loadVirtualEnv(fp, parseOptions{compat: compat})
+		}
+
+		return true
+	}
+
+	if err := quick.Check(check, config); err != nil {
+		t.Error(err)
+	}
+}
+
+func TestLoadVirtualEnvEmptyValues(t *testing.T) {
+	dir, err := ioutil.TempDir("", "dotenv")
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer os.RemoveAll(dir)
+
+	fp := filepath.Join(dir, "app.env")
+	if err := ioutil.WriteFile(fp, []byte("DOUBLE=\"\"\nSINGLE=''\nBARE=\nSET=value\n"), 0644); err != nil {
+		t.Fatal(err)
+	}
+
+	want := map[string]string{"DOUBLE": "", "SINGLE": "", "SET": "value"}
+	if got, err := loadVirtualEnv(fp, parseOptions{}); err != nil || !reflect.DeepEqual(got, want) {
+		t.Errorf("loadVirtualEnv() = %v, %v, want %v", got, err, want)
+	}
+}
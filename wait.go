@@ -0,0 +1,42 @@
+package main
+
+import (
+	"fmt"
+	"net"
+	"time"
+)
+
+const (
+	// waitTimeoutCode is the exit code used when --wait-for gives up,
+	// matching the one used by timeout(1).
+	waitTimeoutCode = 124
+
+	// waitInterval is the time between connection attempts.
+	waitInterval = 500 * time.Millisecond
+)
+
+// waitFor blocks until a TCP connection can be opened to every one of the
+// given "host:port" addresses, one after the other, or until timeout is
+// over, in which case it returns an error naming the address that wasn't
+// reachable.
+func waitFor(addresses []string, timeout time.Duration) error {
+	deadline := time.Now().Add(timeout)
+
+	for _, addr := range addresses {
+		for {
+			conn, err := net.DialTimeout("tcp", addr, waitInterval)
+			if err == nil {
+				conn.Close()
+				break
+			}
+
+			if time.Now().After(deadline) {
+				return fmt.Errorf("%s not reachable after %s: %s", addr, timeout, err.Error())
+			}
+
+			time.Sleep(waitInterval)
+		}
+	}
+
+	return nil
+}
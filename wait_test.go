@@ -0,0 +1,76 @@
+package main
+
+import (
+	"io/ioutil"
+	"net"
+	"os"
+	"os/exec"
+	"path/filepath"
+	"testing"
+	"time"
+)
+
+// closedAddress returns the address of a local port nothing listens on.
+func closedAddress(t *testing.T) string {
+	ln, err := net.Listen("tcp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatal(err)
+	}
+
+	addr := ln.Addr().String()
+	ln.Close()
+
+	return addr
+}
+
+func TestWaitFor(t *testing.T) {
+	ln, err := net.Listen("tcp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer ln.Close()
+
+	if err := waitFor([]string{ln.Addr().String()}, time.Second); err != nil {
+		t.Errorf("waitFor() a listening port error = %v", err)
+	}
+
+	closed := closedAddress(t)
+
+	start := time.Now()
+	if err := waitFor([]string{ln.Addr().String(), closed}, 200*time.Millisecond); err == nil {
+		t.Errorf("waitFor() a closed port succeeded, want an error")
+	}
+
+	if elapsed := time.Since(start); elapsed > 5*time.Second {
+		t.Errorf("waitFor() a closed port took %s, want it to give up after the timeout", elapsed)
+	}
+}
+
+func TestWaitForExitCode(t *testing.T) {
+	// the test binary runs dotenv itself, which exits before running the
+	// command once it gives up waiting
+	if os.Getenv("DOTENV_TEST_WAIT") == "1" {
+		os.Args = []string{"dotenv", "--no-rc", "-f", os.Getenv("DOTENV_TEST_FILE"), "--wait-for", os.Getenv("DOTENV_TEST_ADDR"), "--wait-timeout", "100ms", "true"}
+		main()
+		return
+	}
+
+	dir, err := ioutil.TempDir("", "dotenv")
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer os.RemoveAll(dir)
+
+	fp := filepath.Join(dir, ".env")
+	if err := ioutil.WriteFile(fp, []byte("KEY=value\n"), 0644); err != nil {
+		t.Fatal(err)
+	}
+
+	cmd := exec.Command(os.Args[0], "-test.run=^TestWaitForExitCode$")
+	cmd.Env = append(os.Environ(), "DOTENV_TEST_WAIT=1", "DOTENV_TEST_FILE="+fp, "DOTENV_TEST_ADDR="+closedAddress(t))
+
+	err = cmd.Run()
+	if e, ok := err.(*exec.ExitError); !ok || e.ExitCode() != waitTimeoutCode {
+		t.Errorf("dotenv --wait-for a closed port exited with %v, want exit code %d", err, waitTimeoutCode)
+	}
+}